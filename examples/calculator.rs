@@ -4,6 +4,8 @@ use rtor::{
     Parser,
     ParseError,
     AsChar,
+    State,
+    SimpleError,
     char::{
         char,
         ascii
@@ -15,17 +17,42 @@ use rtor::{
         opt,
         alt,
         eof,
+        limit_depth,
+        recursive,
+    },
+    pratt::{
+        Assoc,
+        Pratt,
     },
 };
 
+type Span<'a> = State<&'a str>;
+
+/// Errors are boxed, a `State` makes them too large to return by value.
+type Error<'a> = Box<SimpleError<Span<'a>>>;
+
+/// How deeply parentheses and operators may nest before parsing gives up.
+const MAX_DEPTH: usize = 256;
+
 fn main() {
-    let result = calc("1 + 2 * ( 3 + 4 ) + 5 * 6");
-    assert_eq!(result, Ok((45.0, "")));
+    let parens = "(".repeat(100_000);
+    let negations = "-".repeat(100_000) + "1";
+    let mut calc = calculator();
+
+    let result = calc.parse(State::new("1 + 2 * ( 3 + 4 ) + 5 * 6"));
+    assert_eq!(result.map(|(v, _)| v).ok(), Some(45.0));
+
+    let result = calc.parse(State::new("-( 1 + 2 ) * 2 ^ 3 ^ 2"));
+    assert_eq!(result.map(|(v, _)| v).ok(), Some(-1536.0));
+
+    assert!(calc.parse(State::new(&parens)).is_err());
+    assert!(calc.parse(State::new(&negations)).is_err());
 }
 
 #[derive(Debug)]
 enum Expr {
     Value(f64),
+    Negate(Box<Expr>),
     Binary {
         op: char,
         left: Box<Expr>,
@@ -37,47 +64,51 @@ impl Expr {
     pub fn eval(&self) -> f64 {
         match self {
             Self::Value(v) => *v,
+            Self::Negate(e) => -e.eval(),
             Self::Binary { op, left, right } => match op {
                 '+' => left.eval() + right.eval(),
                 '-' => left.eval() - right.eval(),
                 '*' => left.eval() * right.eval(),
                 '/' => left.eval() / right.eval(),
+                '^' => left.eval().powf(right.eval()),
                 _ => panic!("unknown binary operator")
             }
         }
     }
 }
 
-fn calc(input: &str) -> ParseResult<f64, &str> {
+/// Builds the grammar once, it is reused for every input parsed with it.
+fn calculator<'a>() -> impl Parser<Span<'a>, Error<'a>, Output = f64> {
+    let expr = recursive(|expr| {
+        let atom = alt((
+            number.map(Expr::Value),
+            between(lexeme(char('(')), limit_depth(MAX_DEPTH, expr), lexeme(char(')')))
+        ));
+
+        Pratt::new(atom)
+            .infix(Assoc::Left, 1, alt((lexeme(char('+')), lexeme(char('-')))), binary)
+            .infix(Assoc::Left, 2, alt((lexeme(char('*')), lexeme(char('/')))), binary)
+            .infix(Assoc::Right, 3, lexeme(char('^')), binary)
+            .prefix(2, lexeme(char('-')), |_, e| Expr::Negate(Box::new(e)))
+            .limit_depth(MAX_DEPTH)
+    });
+
     between(
         ascii::multi_space, 
-        expr.map(|e| e.eval()), 
+        expr.map(|e: Expr| e.eval()), 
         eof
-    )(input)
+    )
 }
 
-fn expr(input: &str) -> ParseResult<Expr, &str> {
-    let atom = alt((
-        number.map(Expr::Value),
-        between(lexeme(char('(')), expr, lexeme(char(')')))
-    ));
-
-    atom.chainl1(|i| {
-            let (op, i) = alt((lexeme(char('*')), lexeme(char('/'))))(i)?;
-            Ok((move |l: Expr, r: Expr| Expr::Binary { op, left: Box::new(l), right: Box::new(r) }, i))
-        })
-        .chainl1(|i| {
-            let (op, i) = alt((lexeme(char('+')), lexeme(char('-'))))(i)?;
-            Ok((move |l: Expr, r: Expr| Expr::Binary { op, left: Box::new(l), right: Box::new(r) }, i))
-        })
-        .parse(input)
+fn binary(left: Expr, op: char, right: Expr) -> Expr {
+    Expr::Binary { op, left: Box::new(left), right: Box::new(right) }
 }
 
-pub fn number(input: &str) -> ParseResult<f64, &str> {
+pub fn number(input: Span) -> ParseResult<f64, Span, Error> {
     let exponent = (alt((char('e'), char('E'))), opt(alt((char('+'), char('-')))), ascii::multi_digit1);
     let fraction = (char('.'), ascii::multi_digit1);
     lexeme(recognize((opt(char('-')), ascii::multi_digit1, opt(fraction), opt(exponent)))
-        .map(|i: &str| i.parse().unwrap()))(input)
+        .map(|i: Span| i.parse().unwrap()))(input)
 }
 
 pub fn lexeme<P, I, E>(parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E> 
@@ -6,6 +6,8 @@ use rtor::{
     ParseResult,
     ParseError,
     AsChar,
+    State,
+    SimpleError,
     char::{
         one_of,
         ascii, 
@@ -26,9 +28,19 @@ use rtor::{
         alt,
         eof,
         opt,
+        cut,
+        limit_depth,
     },
 };
 
+type Span<'a> = State<&'a str>;
+
+/// Errors are boxed, a `State` makes them too large to return by value.
+type Error<'a> = Box<SimpleError<Span<'a>>>;
+
+/// How deeply arrays and objects may nest before parsing gives up.
+const MAX_DEPTH: usize = 256;
+
 fn main() {
     let s = r#"
     {
@@ -39,9 +51,13 @@ fn main() {
     "#;
 
     let result = parse_json(s);
-    println!("{:#?}", result);
+    println!("{:#?}", result.map(|(value, _)| value));
+
+    let deep = "[".repeat(100_000);
+    assert!(parse_json(&deep).is_err());
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 enum JsonValue {
     Object(HashMap<String, JsonValue>),
@@ -52,50 +68,50 @@ enum JsonValue {
     Null
 }
 
-fn parse_json(input: &str) -> ParseResult<JsonValue, &str> {
+fn parse_json(input: &str) -> ParseResult<JsonValue, Span<'_>, Error<'_>> {
     between(
         ascii::multi_space, 
         json_value,
         eof
-    )(input)
+    )(State::new(input))
 }
 
 //https://www.json.org/json-en.html
-fn json_value(input: &str) -> ParseResult<JsonValue, &str> {
-    alt((
+fn json_value(input: Span) -> ParseResult<JsonValue, Span, Error> {
+    limit_depth(MAX_DEPTH, alt((
         between(
             lexeme(char('{')), 
             sep_by(pair(key, lexeme(char(':')), json_value), lexeme(char(','))),
-            lexeme(char('}'))
+            cut(lexeme(char('}')))
         ).map(|members| JsonValue::Object(HashMap::from_iter(members))),
         between(
             lexeme(char('[')),
             sep_by(json_value, lexeme(char(','))),
-            lexeme(char(']'))
+            cut(lexeme(char(']')))
         ).map(JsonValue::Array),
         number.map(JsonValue::Number),
         key.map(JsonValue::String),
         value(JsonValue::Boolean(true), lexeme(string("true"))),
         value(JsonValue::Boolean(false), lexeme(string("false"))),
         value(JsonValue::Null, lexeme(string("null"))),
-    ))(input)
+    )))(input)
 }
 
-fn key(input: &str) -> ParseResult<String, &str> {
+fn key(input: Span) -> ParseResult<String, Span, Error> {
     let escape = alt((one_of("\"\\/bfnrt"), char('u').andl(skip(ascii::hex, 4))));
     let character = alt((char('\\').andl(escape), not(char('"')).andr(anychar)));
     between(
         char('"'), 
-        recognize(skip_many(character)).map(|i: &str| i.to_owned()),
+        recognize(skip_many(character)).map(|i: Span| i.to_string()),
         lexeme(char('"'))
     )(input)
 }
 
-pub fn number(input: &str) -> ParseResult<f64, &str> {
+pub fn number(input: Span) -> ParseResult<f64, Span, Error> {
     let exponent = (alt((char('e'), char('E'))), opt(alt((char('+'), char('-')))), ascii::multi_digit1);
     let fraction = (char('.'), ascii::multi_digit1);
     lexeme(recognize((opt(char('-')), ascii::multi_digit1, opt(fraction), opt(exponent)))
-        .map(|i: &str| i.parse().unwrap()))(input)
+        .map(|i: Span| i.parse().unwrap()))(input)
 }
 
 pub fn lexeme<P, I, E>(parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E> 
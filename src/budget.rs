@@ -0,0 +1,168 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::Exhausted;
+
+/// Limits on how long a parse may run, for grammars or inputs that aren't trusted and
+/// could backtrack for practically ever.
+///
+/// A [`State`](crate::State) given the budget with [`metering`](crate::State::metering)
+/// is charged one unit by [`alt`](crate::combinator::alt) for every alternative it
+/// tries, which covers grammars that backtrack through `alt`. The other combinators that
+/// backtrack, [`or`](crate::Parser::or), [`opt`](crate::combinator::opt),
+/// [`many`](crate::combinator::many), [`sep_by`](crate::combinator::sep_by),
+/// [`chainl1`](crate::Parser::chainl1), [`not`](crate::combinator::not),
+/// [`cond`](crate::combinator::cond) and the like, only need their input to be `Clone`
+/// and don't charge it, so wrap what they repeat or retry in
+/// [`metered`](crate::combinator::metered), which charges the budget each time it runs
+/// on any input. Once the budget runs out of fuel, its cancellation flag is set or its
+/// deadline passes, parsing fails with an [exhausted](crate::ParseError::exhausted)
+/// error. Clones share the fuel, so one budget covers the whole parse however many
+/// inputs and parsers it is given to.
+///
+/// The clock is read once every [`CLOCK_INTERVAL`](Budget::CLOCK_INTERVAL) charges, so
+/// a parse can run that many charges past its deadline.
+/// # Example
+/// ```
+/// use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+/// use rtor::{Parser, ParseError, SimpleError, Budget, Exhausted};
+/// use rtor::char::char;
+/// use rtor::combinator::{many, metered};
+///
+/// let budget = Budget::new().fuel(3);
+/// let err: SimpleError<&str> = many(metered(&budget, char('a'))).parse("aaaaa").unwrap_err();
+/// assert_eq!((err.exhaustion(), err.input), (Some(Exhausted::Fuel), "aa"));
+/// assert_eq!(err.to_string(), "parse ran out of fuel");
+///
+/// let cancel = Arc::new(AtomicBool::new(false));
+/// let budget = Budget::new().cancel_on(cancel.clone());
+/// cancel.store(true, Ordering::Relaxed);
+/// let err: SimpleError<&str> = metered(&budget, char('a')).parse("a").unwrap_err();
+/// assert_eq!(err.exhaustion(), Some(Exhausted::Cancelled));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    fuel: Option<Arc<AtomicUsize>>,
+    cancel: Option<Arc<AtomicBool>>,
+    deadline: Option<(Instant, Arc<AtomicUsize>)>,
+}
+
+impl Budget {
+    /// How many charges there are between reads of the clock.
+    pub const CLOCK_INTERVAL: usize = 64;
+
+    /// A budget without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows `fuel` charges in all.
+    pub fn fuel(self, fuel: usize) -> Self {
+        Self { fuel: Some(Arc::new(AtomicUsize::new(fuel))), ..self }
+    }
+
+    /// Stops the parse once `flag` is set, from this thread or another.
+    pub fn cancel_on(self, flag: Arc<AtomicBool>) -> Self {
+        Self { cancel: Some(flag), ..self }
+    }
+
+    /// Stops the parse once `deadline` has passed.
+    pub fn deadline(self, deadline: Instant) -> Self {
+        Self { deadline: Some((deadline, Arc::default())), ..self }
+    }
+
+    /// Stops the parse once `timeout` has passed from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// The fuel left, if the budget has a limit on it.
+    pub fn remaining(&self) -> Option<usize> {
+        self.fuel.as_ref().map(|fuel| fuel.load(Ordering::Relaxed))
+    }
+
+    /// Takes `cost` fuel and checks the other limits, failing with the first that was
+    /// hit. Fuel is not taken when there isn't enough of it left.
+    pub fn charge(&self, cost: usize) -> Result<(), Exhausted> {
+        if self.cancel.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Err(Exhausted::Cancelled);
+        }
+        if let Some((deadline, charges)) = &self.deadline {
+            let charged = charges.fetch_add(1, Ordering::Relaxed);
+            if charged % Self::CLOCK_INTERVAL == 0 && Instant::now() >= *deadline {
+                return Err(Exhausted::Deadline);
+            }
+        }
+        if let Some(fuel) = &self.fuel {
+            fuel.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| left.checked_sub(cost))
+                .map_err(|_| Exhausted::Fuel)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ParseResult, ParseError, SimpleError, State};
+    use crate::char::char;
+    use crate::combinator::{alt, many, metered};
+
+    type Text<'a> = State<&'a str>;
+
+    /// Tries every way of splitting the input into `a`s and `aa`s before failing at the
+    /// end, which takes exponential time.
+    fn split(i: Text) -> ParseResult<(), Text, Box<SimpleError<Text>>> {
+        alt((
+            char('a').andr(char('a')).andr(split),
+            char('a').andr(split),
+            char('!').map(|_| ()),
+        )).parse(i)
+    }
+
+    #[test]
+    fn alternatives_charge_the_state() {
+        let budget = Budget::new().fuel(10_000);
+        let source = "a".repeat(64);
+        let err = split.parse(State::new(source.as_str()).metering(&budget)).unwrap_err();
+        assert_eq!(err.exhaustion(), Some(Exhausted::Fuel));
+        assert!(err.is_committed());
+    }
+
+    #[test]
+    fn metered_repetition_charges_each_run() {
+        let budget = Budget::new().fuel(3);
+        let mut parser = many(metered(&budget, char('a')));
+        let result: ParseResult<Vec<char>, Text, Box<SimpleError<Text>>> = parser.parse(State::new("aab"));
+        assert_eq!(result.unwrap().0, ['a', 'a']);
+        assert_eq!(budget.remaining(), Some(0));
+    }
+
+    #[test]
+    fn deadline_is_checked_every_interval() {
+        let budget = Budget::new().deadline(Instant::now());
+        assert_eq!(budget.charge(1), Err(Exhausted::Deadline));
+        for _ in 1..Budget::CLOCK_INTERVAL {
+            assert_eq!(budget.charge(1), Ok(()));
+        }
+        assert_eq!(budget.charge(1), Err(Exhausted::Deadline));
+    }
+
+    #[test]
+    fn cancelled_state_stops_at_the_next_alternative() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        let budget = Budget::new().cancel_on(cancel);
+        let err = split.parse(State::new("aa!").metering(&budget)).unwrap_err();
+        assert_eq!(err.exhaustion(), Some(Exhausted::Cancelled));
+    }
+
+    #[test]
+    fn unmetered_states_are_not_charged() {
+        assert!(split.parse(State::new("aaaa!")).is_ok());
+    }
+}
@@ -5,6 +5,8 @@ use crate::{
     FindToken, 
     ParseResult, 
     ParseError,
+    Expected,
+    Needed,
     combinator::{
         take_while,
         take_while1
@@ -21,8 +23,8 @@ use crate::{
 ///     char('a')(i)
 /// }
 /// 
-/// assert_eq!(parser("abc"), Ok((Some('a'), "bc")));
-/// assert_eq!(parser("bbc"), Err(SimpleError::Unexpected(Some('b')));
+/// assert_eq!(parser("abc"), Ok(('a', "bc")));
+/// assert_eq!(parser("bbc"), Err(SimpleError { input: "bbc", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn char<I, E>(ch: char) -> impl FnMut(I) -> ParseResult<I::Token, I, E>
 where
@@ -30,7 +32,13 @@ where
     I::Token: AsChar,
     E: ParseError<I>
 {
-    sat(move |t: &I::Token| t.as_char() == ch)
+    move |mut input: I| {
+        match input.peek() {
+            Some(t) if t.as_char() == ch => { input.next(); Ok((t, input)) },
+            None if input.is_partial() => Err(ParseError::incomplete(Needed::Size(ch.len_utf8()), input)),
+            _ => Err(ParseError::expected(Expected::Char(ch), input)),
+        }
+    }
 }
 
 pub fn string<I, E>(string: &str) -> impl FnMut(I) -> ParseResult<I, I, E> + '_ 
@@ -39,15 +47,22 @@ where
     I::Token: AsChar,
     E: ParseError<I>
 {
-    move |mut input: I| {
-        let src = input.clone();
+    move |input: I| {
+        if let Some(offset) = input.starts_with(string) {
+            return Ok(input.split_at(offset));
+        }
 
-        for ch in string.chars() {
-            let (_, i) = char(ch)(input)?;
-            input = i;
+        // fail where the input stops matching, as matching char by char would
+        let mut rest = input.clone();
+        for (i, ch) in string.char_indices() {
+            match rest.peek() {
+                Some(t) if t.as_char() == ch => { rest.next(); },
+                None if rest.is_partial() => return Err(ParseError::incomplete(Needed::Size(string.len() - i), input)),
+                _ => break
+            }
         }
-        
-        Ok((src.diff(&input), input))
+
+        Err(ParseError::expected(Expected::Str(string.to_owned()), rest))
     }
 }
 
@@ -60,9 +75,12 @@ where
     move |mut input: I| {
         let src = input.clone();
 
-        for ch in string.chars() {
-            let (_, i) = sat(|t: &I::Token| t.as_char().eq_ignore_ascii_case(&ch))(input)?;
-            input = i;
+        for (i, ch) in string.char_indices() {
+            match input.peek() {
+                Some(t) if t.as_char().eq_ignore_ascii_case(&ch) => { input.next(); },
+                None if input.is_partial() => return Err(ParseError::incomplete(Needed::Size(string.len() - i), src)),
+                _ => return Err(ParseError::expected(Expected::Str(string.to_owned()), input)),
+            }
         }
         
         Ok((src.diff(&input), input))
@@ -78,7 +96,8 @@ where
 {
     move |mut input: I| {
         match input.peek() {
-            Some(t) if pred(&t) => {input.next(); return Ok((t, input))},
+            Some(t) if pred(&t) => { input.next(); Ok((t, input)) },
+            None if input.is_partial() => Err(ParseError::incomplete(Needed::Size(1), input)),
             _ => Err(ParseError::unexpect(input)),
         }
     }
@@ -482,3 +501,28 @@ pub mod unicode {
         take_while1(|t: &I::Token| t.as_char().is_whitespace())(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, SimpleError};
+
+    #[test]
+    fn string_fails_where_the_input_differs() {
+        let err: SimpleError<&str> = string("abc").parse("abx").unwrap_err();
+        assert_eq!(err.input, "x");
+        let err: SimpleError<&str> = string_no_case("ABC").parse("abx").unwrap_err();
+        assert_eq!(err.input, "x");
+    }
+
+    #[test]
+    fn string_matches_bytes() {
+        let bytes = "é!".as_bytes();
+        let result: ParseResult<&[u8], &[u8], SimpleError<&[u8]>> = string("é").parse(bytes);
+        assert!(result.is_err());
+        let result: ParseResult<&[u8], &[u8], SimpleError<&[u8]>> = string("\u{c3}\u{a9}").parse(bytes);
+        assert_eq!(result, Ok((&bytes[..2], &bytes[2..])));
+        let result: ParseResult<&[u8], &[u8], SimpleError<&[u8]>> = string("é!").parse(b"\xc3\xa9?".as_slice());
+        assert_eq!(result.unwrap_err().input, b"\xc3\xa9?");
+    }
+}
@@ -0,0 +1,309 @@
+use std::fmt;
+
+use crate::{
+    Input,
+    Location,
+};
+
+/// An input over text stored as a list of chunks, like the pieces of a rope in an
+/// editor buffer, parsed in place without joining them into one string.
+///
+/// Tokens run across chunk boundaries as if the text was contiguous, and `diff` returns
+/// another `Chunked` over the same chunks. Its location is the byte offset into the text.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, Chunked};
+/// use rtor::char::{char, ascii::multi_alpha1};
+/// use rtor::combinator::sep_by;
+///
+/// fn words(i: Chunked) -> ParseResult<Vec<Chunked>, Chunked> {
+///     sep_by(multi_alpha1, char(' ')).parse(i)
+/// }
+///
+/// let chunks = ["hel", "lo wo", "", "rld"];
+/// let (words, _) = words(Chunked::new(&chunks)).unwrap();
+///
+/// assert_eq!(words[1].pieces().collect::<Vec<_>>(), ["wo", "rld"]);
+/// assert_eq!(words[1].to_string(), "world");
+/// assert_eq!(words[1], "world");
+/// ```
+#[derive(Clone, Copy)]
+pub struct Chunked<'a> {
+    chunks: &'a [&'a str],
+    chunk: usize,
+    offset: usize,
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Chunked<'a> {
+    pub fn new(chunks: &'a [&'a str]) -> Self {
+        Self {
+            chunks,
+            chunk: 0,
+            offset: 0,
+            pos: 0,
+            end: chunks.iter().map(|c| c.len()).sum(),
+        }
+    }
+
+    /// The parts of the chunks this input covers, in order.
+    pub fn pieces(&self) -> impl Iterator<Item = &'a str> {
+        let mut input = *self;
+        std::iter::from_fn(move || {
+            while input.pos < input.end {
+                let chunk = input.chunks[input.chunk];
+                let piece = &chunk[input.offset..chunk.len().min(input.offset + input.end - input.pos)];
+                input.pos += piece.len();
+                input.chunk += 1;
+                input.offset = 0;
+                if !piece.is_empty() {
+                    return Some(piece);
+                }
+            }
+            None
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.end
+    }
+
+    /// Moves forward to the chunk holding the next byte, if there is one.
+    fn settle(&mut self) {
+        while self.chunk + 1 < self.chunks.len() && self.offset == self.chunks[self.chunk].len() {
+            self.chunk += 1;
+            self.offset = 0;
+        }
+    }
+
+    /// This input moved forward to `pos`, or `None` if it is not on a char boundary.
+    fn at(&self, pos: usize) -> Option<Self> {
+        let mut input = *self;
+        let mut remaining = pos.min(self.end) - self.pos.min(pos);
+        while remaining > 0 {
+            let available = input.chunks[input.chunk].len() - input.offset;
+            if remaining <= available {
+                input.offset += remaining;
+                break;
+            }
+            remaining -= available;
+            input.chunk += 1;
+            input.offset = 0;
+        }
+        input.pos = pos.min(self.end).max(self.pos);
+        input.settle();
+        match input.chunks.get(input.chunk) {
+            Some(chunk) if !chunk.is_char_boundary(input.offset) => None,
+            _ => Some(input)
+        }
+    }
+}
+
+impl<'a> Input for Chunked<'a> {
+    type Token = char;
+    type Tokens = Chars<'a>;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        let ch = self.peek()?;
+        self.offset += ch.len_utf8();
+        self.pos += ch.len_utf8();
+        self.settle();
+        Some(ch)
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        if self.pos >= self.end {
+            return None;
+        }
+        self.settle();
+        self.chunks[self.chunk][self.offset..].chars().next()
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        let offset = self.offset_to(other).unwrap_or(0);
+        Chunked { end: self.pos + offset, ..*self }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        Chars { input: *self }
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        let same = std::ptr::eq(self.chunks, other.chunks);
+        let offset = other.pos.checked_sub(self.pos)?;
+        (same && other.pos <= self.end).then_some(offset)
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> {
+        let mut rest = literal.as_bytes();
+        for piece in self.pieces() {
+            if rest.is_empty() {
+                break;
+            }
+            let n = piece.len().min(rest.len());
+            if piece.as_bytes()[..n] != rest[..n] {
+                return None;
+            }
+            rest = &rest[n..];
+        }
+        rest.is_empty().then_some(literal.len())
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        // back up to the start of the char the offset falls inside
+        let mut pos = self.pos.saturating_add(offset).min(self.end);
+        let rest = loop {
+            match self.at(pos) {
+                Some(rest) => break rest,
+                None => pos -= 1
+            }
+        };
+        (self.diff(&rest), rest)
+    }
+
+    fn split_at_position<P>(&self, mut pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let mut offset = 0;
+        'pieces: for piece in self.pieces() {
+            for (i, ch) in piece.char_indices() {
+                if pred(&ch) {
+                    offset += i;
+                    break 'pieces;
+                }
+            }
+            offset += piece.len();
+        }
+        self.split_at(offset)
+    }
+
+    fn len_utf8(&self) -> usize {
+        self.end - self.pos
+    }
+}
+
+impl Location for Chunked<'_> {
+    type Location = usize;
+
+    fn location(&self) -> Self::Location {
+        self.pos
+    }
+}
+
+impl fmt::Display for Chunked<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pieces().try_for_each(|piece| f.write_str(piece))
+    }
+}
+
+impl fmt::Debug for Chunked<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+impl PartialEq for Chunked<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.tokens().eq(other.tokens())
+    }
+}
+
+impl PartialEq<str> for Chunked<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.len() == other.len() && self.starts_with(other).is_some()
+    }
+}
+
+impl PartialEq<&str> for Chunked<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+/// The characters of a [`Chunked`] input.
+#[derive(Debug, Clone)]
+pub struct Chars<'a> {
+    input: Chunked<'a>,
+}
+
+impl Iterator for Chars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.input.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(input: Chunked) -> String {
+        input.tokens().collect()
+    }
+
+    #[test]
+    fn empty_chunks_at_the_ends_and_between() {
+        let chunks = ["", "", "ab", "", "", "c", ""];
+        let mut input = Chunked::new(&chunks);
+        assert_eq!(chars(input), "abc");
+        assert_eq!(input.pieces().collect::<Vec<_>>(), ["ab", "c"]);
+        input.next();
+        input.next();
+        assert_eq!((input.peek(), input.location()), (Some('c'), 2));
+        input.next();
+        assert_eq!((input.next(), input.is_empty()), (None, true));
+    }
+
+    #[test]
+    fn only_empty_chunks() {
+        for chunks in [&[][..], &[""][..], &["", ""][..]] {
+            let mut input = Chunked::new(chunks);
+            assert_eq!((input.peek(), input.len()), (None, 0));
+            assert_eq!(input.split_at(3).1.location(), 0);
+            assert_eq!(input.starts_with(""), Some(0));
+            assert_eq!(input.starts_with("a"), None);
+        }
+    }
+
+    #[test]
+    fn split_at_a_boundary_with_empty_chunks() {
+        let chunks = ["ab", "", "", "cd"];
+        let input = Chunked::new(&chunks);
+        let (head, rest) = input.split_at(2);
+        assert_eq!((head.to_string(), rest.to_string()), ("ab".to_owned(), "cd".to_owned()));
+        assert_eq!(head.pieces().collect::<Vec<_>>(), ["ab"]);
+        assert_eq!(input.offset_to(&rest), Some(2));
+
+        let (head, rest) = input.split_at_position(|ch| *ch == 'c');
+        assert_eq!((head, rest), (Chunked::new(&["ab"]), Chunked::new(&["cd"])));
+    }
+
+    #[test]
+    fn split_inside_a_char_splits_before_it() {
+        let chunks = ["a", "", "éb"];
+        let input = Chunked::new(&chunks);
+        let (head, rest) = input.split_at(2);
+        assert_eq!((head.to_string(), rest.to_string()), ("a".to_owned(), "éb".to_owned()));
+        assert_eq!(rest.location(), 1);
+    }
+
+    #[test]
+    fn literals_and_diffs_run_across_empty_chunks() {
+        let chunks = ["é", "", "x", "", "yz"];
+        let input = Chunked::new(&chunks);
+        assert_eq!(input.starts_with("éxy"), Some(4));
+        assert_eq!(input.starts_with("éxz"), None);
+
+        let mut end = input;
+        for _ in 0..3 {
+            end.next();
+        }
+        let head = input.diff(&end);
+        assert_eq!((head.len(), head.to_string()), (4, "éxy".to_owned()));
+        assert_eq!(head.pieces().collect::<Vec<_>>(), ["é", "x", "y"]);
+    }
+}
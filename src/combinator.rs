@@ -3,6 +3,14 @@ use crate::{
     Input, 
     ParseResult,
     ParseError, 
+    Expected,
+    Needed,
+    UserState,
+    Depth,
+    Budget,
+    Recursive,
+    Memoize,
+    MemoTable,
     Alt, 
     Seq, 
 };
@@ -25,16 +33,41 @@ use crate::{
 pub fn opt<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<Option<P::Output>, I, E>
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>,
 {
     move |input: I| {
         match parser.parse(input.clone()) {
             Ok((o, i)) => Ok((Some(o), i)),
+            Err(e) if e.is_committed() => Err(e),
             Err(_) => Ok((None, input))
         }
     }
 }
 
+/// Apply `parser`, if fails, the error is committed so enclosing alternatives stop trying
+/// and report it as is.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, SimpleError};
+/// use rtor::char::char;
+/// use rtor::combinator::{alt, cut, preceded};
+/// 
+/// fn parser(i: &str) -> ParseResult<char, &str> {
+///     alt((preceded(char('a'), cut(char('b'))), char('a')))(i)
+/// }
+/// 
+/// assert_eq!(parser("abc"), Ok(('b', "c")));
+/// assert_eq!(parser("acb"), Err(SimpleError { input: "cb", message: None, committed: true, needed: None, exhausted: None }));
+/// ```
+pub fn cut<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    E: ParseError<I>,
+    P: Parser<I, E>,
+{
+    move |input: I| parser.parse(input).map_err(ParseError::commit)
+}
+
 /// Apply `parser` between parser `left` and parser `right`, the value returned by `parser`.
 /// # Example
 /// ```
@@ -98,9 +131,10 @@ where
 {
     move |mut input: I| {
         let src = input.clone();
-        for _ in 0..n {
+        for k in 0..n {
             match input.peek() {
                 Some(t) if f(&t) => { input.next(); }
+                None if input.is_partial() => return Err(E::incomplete(Needed::Size(n - k), input)),
                 _ => return Err(E::unexpect(input))
             }
         }
@@ -114,16 +148,12 @@ where
     E: ParseError<I>,
     F: FnMut(&I::Token) -> bool
 {
-    move |mut input: I| {
-        let src = input.clone();
-        while let Some(t) = input.peek() {
-            if f(&t) {
-                input.next();
-                continue;
-            }
-            break;
+    move |input: I| {
+        let (output, mut rest) = input.split_at_position(|t| !f(t));
+        if rest.is_partial() && rest.peek().is_none() {
+            return Err(E::incomplete(Needed::Unknown, rest));
         }
-        Ok((src.diff(&input), input))
+        Ok((output, rest))
     }
 }
 
@@ -134,19 +164,16 @@ where
     F: FnMut(&I::Token) -> bool
 {
     move |mut input: I| {
-        let src = input.clone();
         match input.peek() {
-            Some(t) if f(&t) => { input.next(); }
+            Some(t) if f(&t) => {}
+            None if input.is_partial() => return Err(E::incomplete(Needed::Size(1), input)),
             _ => return Err(E::unexpect(input))
         }
-        while let Some(t) = input.peek() {
-            if f(&t) {
-                input.next();
-                continue;
-            }
-            break;
+        let (output, mut rest) = input.split_at_position(|t| !f(t));
+        if rest.is_partial() && rest.peek().is_none() {
+            return Err(E::incomplete(Needed::Unknown, rest));
         }
-        Ok((src.diff(&input), input))
+        Ok((output, rest))
     }
 }
 
@@ -156,16 +183,13 @@ where
     E: ParseError<I>,
     F: FnMut(&I::Token) -> bool
 {
-    move |mut input: I| {
-        let src = input.clone();
-        loop {
-            match input.peek() {
-                Some(t) if f(&t) => break,
-                Some(_) => { input.next(); }
-                None => return Err(E::unexpect(input))
-            }
+    move |input: I| {
+        let (output, mut rest) = input.split_at_position(&mut f);
+        match rest.peek() {
+            Some(_) => Ok((output, rest)),
+            None if rest.is_partial() => Err(E::incomplete(Needed::Unknown, rest)),
+            None => Err(E::unexpect(rest))
         }
-        Ok((src.diff(&input), input))
     }
 }
 
@@ -176,19 +200,17 @@ where
     F: FnMut(&I::Token) -> bool
 {
     move |mut input: I| {
-        let src = input.clone();
         match input.peek() {
             Some(t) if f(&t) => return Err(E::unexpect(input)),
-            _ => { input.next(); }
+            None if input.is_partial() => return Err(E::incomplete(Needed::Size(1), input)),
+            _ => {}
         }
-        loop {
-            match input.peek() {
-                Some(t) if f(&t) => break,
-                Some(_) => { input.next(); }
-                None => return Err(E::unexpect(input))
-            }
+        let (output, mut rest) = input.split_at_position(&mut f);
+        match rest.peek() {
+            Some(_) => Ok((output, rest)),
+            None if rest.is_partial() => Err(E::incomplete(Needed::Unknown, rest)),
+            None => Err(E::unexpect(rest))
         }
-        Ok((src.diff(&input), input))
     }
 }
 
@@ -209,15 +231,21 @@ where
 pub fn many<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>,
 {
     move |mut input: I| {
         let mut result = vec![];
-        while let Ok((o, i)) = parser.parse(input.clone()) {
-            result.push(o);
-            input = i;
+        loop {
+            match parser.parse(input.clone()) {
+                Ok((o, i)) => {
+                    result.push(o);
+                    input = i;
+                }
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok((result, input))
+            }
         }
-        Ok((result, input))
     }
 }
 
@@ -233,21 +261,27 @@ where
 /// }
 /// 
 /// assert_eq!(parser("aaab"), Ok((vec!['a', 'a', 'a'], "b")));
-/// assert_eq!(parser("b"), Err(SimpleError::Unexpected(Some('b'))));
+/// assert_eq!(parser("b"), Err(SimpleError { input: "b", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn many1<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>
 {
     move |input: I| {
         let (o, mut input) = parser.parse(input)?;
         let mut result = vec![o];
-        while let Ok((o, i)) = parser.parse(input.clone()) {
-            result.push(o);
-            input = i;
+        loop {
+            match parser.parse(input.clone()) {
+                Ok((o, i)) => {
+                    result.push(o);
+                    input = i;
+                }
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok((result, input))
+            }
         }
-        Ok((result, input))
     }
 }
 
@@ -265,23 +299,29 @@ where
 /// 
 /// assert_eq!(parser("aaab"), Ok((vec!['a', 'a', 'a'], "b")));
 /// assert_eq!(parser("b"), Ok((vec![], "b")));
-/// assert_eq!(parser("acb"), Err(SimpleError::Unexpected(Some('c'))));
-/// assert_eq!(parser("aa"), Err(SimpleError::Unexpected(None)));
+/// assert_eq!(parser("acb"), Err(SimpleError { input: "cb", message: None, committed: false, needed: None, exhausted: None }));
+/// assert_eq!(parser("aa"), Err(SimpleError { input: "", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn many_till<P, F, I, E>(mut parser: P, mut f: F) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E>  
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>,
     F: Parser<I, E>,
 {
     move |mut input: I| {
         let mut result = vec![];
-        while let Err(_) = f.parse(input.clone()) {
-            let (o, i) = parser.parse(input)?;
-            result.push(o);
-            input = i;
+        loop {
+            match f.parse(input.clone()) {
+                Ok(_) => return Ok((result, input)),
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => {
+                    let (o, i) = parser.parse(input)?;
+                    result.push(o);
+                    input = i;
+                }
+            }
         }
-        Ok((result, input))
     }
 }
 
@@ -298,7 +338,7 @@ where
 /// 
 /// assert_eq!(parser("aaa"), Ok((vec!['a', 'a', 'a'], "")));
 /// assert_eq!(parser("aaaa"), Ok((vec!['a', 'a', 'a'], "a")));
-/// assert_eq!(parser("aa"), Err(SimpleError::Unexpected(None)));
+/// assert_eq!(parser("aa"), Err(SimpleError { input: "", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn count<I, E, P>(mut parser: P, n: usize) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E>  
 where 
@@ -332,13 +372,17 @@ where
 pub fn skip_many<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<(), I, E>  
 where 
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>
 {
     move |mut input: I| {
-        while let Ok((_, i)) = parser.parse(input.clone()) {
-            input = i;
+        loop {
+            match parser.parse(input.clone()) {
+                Ok((_, i)) => input = i,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok(((), input))
+            }
         }
-        Ok(((), input))
     }
 }
 
@@ -354,19 +398,23 @@ where
 /// }
 /// 
 /// assert_eq!(parser("aaab"), Ok(((), "b")));
-/// assert_eq!(parser("b"), Err(SimpleError::Unexpected(Some('b'))));
+/// assert_eq!(parser("b"), Err(SimpleError { input: "b", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn skip_many1<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<(), I, E> 
 where 
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>
 {
     move |input: I| {
         let (_, mut input) = parser.parse(input)?;
-        while let Ok((_, i)) = parser.parse(input.clone()) {
-            input = i;
+        loop {
+            match parser.parse(input.clone()) {
+                Ok((_, i)) => input = i,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok(((), input))
+            }
         }
-        Ok(((), input))
     }
 }
 
@@ -383,21 +431,27 @@ where
 /// 
 /// assert_eq!(parser("aaab"), Ok(((), "b")));
 /// assert_eq!(parser("b"), Ok(((), "b")));
-/// assert_eq!(parser("acb"), Err(SimpleError::Unexpected(Some('c'))));
-/// assert_eq!(parser("aa"), Err(SimpleError::Unexpected(None)));
+/// assert_eq!(parser("acb"), Err(SimpleError { input: "cb", message: None, committed: false, needed: None, exhausted: None }));
+/// assert_eq!(parser("aa"), Err(SimpleError { input: "", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn skip_till<P, F, I, E>(mut parser: P, mut f: F) -> impl FnMut(I) -> ParseResult<(), I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>,
     F: Parser<I, E>
 {
     move |mut input: I| {
-        while let Err(_) = f.parse(input.clone()) {
-            let (_, i) = parser.parse(input)?;
-            input = i;
+        loop {
+            match f.parse(input.clone()) {
+                Ok(_) => return Ok(((), input)),
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => {
+                    let (_, i) = parser.parse(input)?;
+                    input = i;
+                }
+            }
         }
-        Ok(((), input))
     }
 }
 
@@ -414,7 +468,7 @@ where
 /// 
 /// assert_eq!(parser("aaa"), Ok(((), "")));
 /// assert_eq!(parser("aaaa"), Ok(((), "a")));
-/// assert_eq!(parser("aa"), Err(SimpleError::Unexpected(None)));
+/// assert_eq!(parser("aa"), Err(SimpleError { input: "", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn skip<I, E, P>(mut parser: P, n: usize) -> impl FnMut(I) -> ParseResult<(), I, E> 
 where 
@@ -447,20 +501,26 @@ where
 pub fn sep_by<I, E, P, S>(mut parser: P, mut sep: S) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>, 
     S: Parser<I, E>
 {
     move |input: I| {
         let (mut result, mut input) = match parser.parse(input.clone()) {
             Ok((o, i)) => (vec![o], i),
+            Err(e) if e.is_committed() => return Err(e),
             Err(_) => return Ok((vec![], input))
         };
-        while let Ok((_, i)) = sep.parse(input.clone()) {
+        loop {
+            let i = match sep.parse(input.clone()) {
+                Ok((_, i)) => i,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok((result, input))
+            };
             let (o, i) = parser.parse(i)?;
             result.push(o);
             input = i;
         }
-        Ok((result, input))
     }
 }
 
@@ -477,23 +537,28 @@ where
 /// 
 /// assert_eq!(parser("a,a,a"), Ok((vec!['a', 'a', 'a'], "")));
 /// assert_eq!(parser("a"), Ok((vec!['a'], "")));
-/// assert_eq!(parser("b"), Err(SimpleError::Unexpected(Some('b'))));
+/// assert_eq!(parser("b"), Err(SimpleError { input: "b", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn sep_by1<I, E, P, S>(mut parser: P, mut sep: S) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>, 
     S: Parser<I, E>
 {
     move |input: I| {
         let (o, mut input) = parser.parse(input)?;
         let mut result = vec![o];
-        while let Ok((_, i)) = sep.parse(input.clone()) {
+        loop {
+            let i = match sep.parse(input.clone()) {
+                Ok((_, i)) => i,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok((result, input))
+            };
             let (o, i) = parser.parse(i)?;
             result.push(o);
             input = i;
         }
-        Ok((result, input))
     }
 }
 
@@ -515,6 +580,7 @@ where
 pub fn end_by<I, E, P, S>(mut parser: P, mut sep: S) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E>
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>, 
     S: Parser<I, E>
 {
@@ -522,16 +588,21 @@ where
         let mut result = vec![];
         let (o, i) = match parser.parse(input.clone()) {
             Ok((o, i)) => (o, i),
+            Err(e) if e.is_committed() => return Err(e),
             Err(_) => return Ok((vec![], input))
         };
         let (_, mut input) = sep.parse(i)?;
         result.push(o);
-        while let Ok((o, i)) = parser.parse(input.clone()) {
+        loop {
+            let (o, i) = match parser.parse(input.clone()) {
+                Ok(t) => t,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok((result, input))
+            };
             let (_, i) = sep.parse(i)?;
             result.push(o);
             input = i;            
         }
-        Ok((result, input))
     }
 }
 
@@ -548,12 +619,13 @@ where
 /// 
 /// assert_eq!(parser("a;a;a;"), Ok((vec!['a', 'a', 'a'], "")));
 /// assert_eq!(parser("a;"), Ok((vec!['a'], "")));
-/// assert_eq!(parser("a"), Err(SimpleError::Unexpected(None)));
-/// assert_eq!(parser("b"), Err(SimpleError::Unexpected(Some('b'))));
+/// assert_eq!(parser("a"), Err(SimpleError { input: "", message: None, committed: false, needed: None, exhausted: None }));
+/// assert_eq!(parser("b"), Err(SimpleError { input: "b", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn end_by1<I, E, P, S>(mut parser: P, mut sep: S) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     P: Parser<I, E>, 
     S: Parser<I, E>
 {
@@ -562,12 +634,16 @@ where
         let (o, i) = parser.parse(input)?;
         let (_, mut input) = sep.parse(i)?;
         result.push(o);
-        while let Ok((o, i)) = parser.parse(input.clone()) {
+        loop {
+            let (o, i) = match parser.parse(input.clone()) {
+                Ok(t) => t,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => return Ok((result, input))
+            };
             let (_, i) = sep.parse(i)?;
             result.push(o);
             input = i;            
         }
-        Ok((result, input))
     }
 }
 
@@ -584,7 +660,7 @@ where
 /// }
 /// 
 /// assert_eq!(parser("abc"), Ok(('a', "abc")));
-/// assert_eq!(parser("cbc"), Err(SimpleError::Unexpected(Some('c'))));
+/// assert_eq!(parser("cbc"), Err(SimpleError { input: "cbc", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn peek<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E> 
 where
@@ -611,16 +687,27 @@ where
 /// }
 /// 
 /// assert_eq!(parser("aaab"), Ok(("aaa", "b")));
+///
+/// // a parser that hands back some other input fails instead of slicing garbage
+/// fn elsewhere(_: &str) -> ParseResult<(), &str> {
+///     Ok(((), "elsewhere"))
+/// }
+///
+/// assert!(recognize(elsewhere)("aaab").is_err());
 /// ```
 pub fn recognize<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<I, I, E>
 where
     I: Input,
+    E: ParseError<I>,
     P: Parser<I, E>
 {
     move |input: I| {
         let src = input.clone();
         let (_, i) = parser.parse(input)?;
-        Ok((src.diff(&i), i))
+        match src.offset_to(&i) {
+            Some(_) => Ok((src.diff(&i), i)),
+            None => Err(ParseError::unexpect(i))
+        }
     }
 }
 
@@ -636,7 +723,7 @@ where
 /// }
 /// 
 /// assert_eq!(parser("ba"), Ok(((), "ba")));
-/// assert_eq!(parser("ab"), Err(SimpleError::Unexpected(Some('a'))));
+/// assert_eq!(parser("ab"), Err(SimpleError { input: "ab", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn not<I, E, P>(mut parser: P) -> impl FnMut(I) -> ParseResult<(), I, E>
 where
@@ -646,6 +733,7 @@ where
 {
     move |input: I| {
         match parser.parse(input.clone()) {
+            Err(e) if e.is_committed() => Err(e),
             Err(_) => Ok(((), input)),
             Ok(_) => Err(ParseError::unexpect(input))
         }
@@ -665,11 +753,12 @@ where
 /// 
 /// assert_eq!(parser("abc"), Ok((Some('b'), "c")));
 /// assert_eq!(parser("cbc"), Ok((None, "cbc")));
-/// assert_eq!(parser("acb"), Err(SimpleError::Unexpected(Some('c'))));
+/// assert_eq!(parser("acb"), Err(SimpleError { input: "cb", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn cond<F, P, I, E>(mut f: F, mut parser: P) -> impl FnMut(I) -> ParseResult<Option<P::Output>, I, E> 
 where
     I: Clone,
+    E: ParseError<I>,
     F: Parser<I, E>,
     P: Parser<I, E>
 {
@@ -679,6 +768,7 @@ where
                 Ok((o, i)) => Ok((Some(o), i)),
                 Err(e) => Err(e)
             }
+            Err(e) if e.is_committed() => Err(e),
             Err(_) => Ok((None, input))
         }
     }
@@ -745,7 +835,7 @@ where
 /// }
 /// 
 /// assert_eq!(parser(""), Ok(((), "")));
-/// assert_eq!(parser("abc"), Err(SimpleError::Unexpected(Some('a'))));
+/// assert_eq!(parser("abc"), Err(SimpleError { input: "abc", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn eof<I, E>(mut input: I) ->  ParseResult<(), I, E>
 where
@@ -753,8 +843,9 @@ where
     E: ParseError<I>
 {
     match input.peek() {
+        None if input.is_partial() => Err(ParseError::incomplete(Needed::Unknown, input)),
         None => Ok(((), input)),
-        Some(_) => Err(ParseError::unexpect(input))
+        Some(_) => Err(ParseError::expected(Expected::Eof, input))
     }
 }
 
@@ -773,7 +864,7 @@ pub fn empty<I, E>(input: I) ->  ParseResult<(), I, E> {
 ///     error(i)
 /// }
 /// 
-/// assert_eq!(parser("abc"), Err(SimpleError::Unexpected(Some('a'))));
+/// assert_eq!(parser("abc"), Err(SimpleError { input: "abc", message: None, committed: false, needed: None, exhausted: None }));
 /// ```
 pub fn error<I, E>(input: I) -> ParseResult<(), I, E> 
 where
@@ -832,6 +923,230 @@ where
     }
 }
 
+/// Returns a copy of the user data of a [`State`](crate::State).
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, State};
+/// use rtor::char::{char, string};
+/// use rtor::combinator::{alt, get_state, modify_state};
+///
+/// type Input<'a> = State<&'a str, u32>;
+///
+/// fn parser(i: Input) -> ParseResult<u32, Input> {
+///     alt((
+///         string("a").andl(modify_state(|n: &mut u32| *n += 1)).andl(char('!')),
+///         string("ab"),
+///     )).andr(get_state).parse(i)
+/// }
+///
+/// // the first alternative's change is undone when it backtracks
+/// assert_eq!(parser(State::with_data(0, "ab")).unwrap().0, 0);
+/// assert_eq!(parser(State::with_data(0, "a!")).unwrap().0, 1);
+/// ```
+pub fn get_state<I, E>(input: I) -> ParseResult<I::Data, I, E>
+where
+    I: UserState,
+    I::Data: Clone
+{
+    Ok((input.data().clone(), input))
+}
+
+/// Replaces the user data of a [`State`](crate::State) with `data`.
+pub fn put_state<I, E>(data: I::Data) -> impl FnMut(I) -> ParseResult<(), I, E>
+where
+    I: UserState,
+    I::Data: Clone
+{
+    move |mut input: I| {
+        input.set_data(data.clone());
+        Ok(((), input))
+    }
+}
+
+/// Changes the user data of a [`State`](crate::State) in place. Earlier inputs that
+/// could be backtracked to keep their own copy.
+pub fn modify_state<F, I, E>(mut f: F) -> impl FnMut(I) -> ParseResult<(), I, E>
+where
+    I: UserState,
+    F: FnMut(&mut I::Data)
+{
+    move |mut input: I| {
+        f(input.data_mut());
+        Ok(((), input))
+    }
+}
+
+/// Builds a parser from the user data of a [`State`](crate::State) and runs it, for grammars whose
+/// rules depend on what was parsed before, like operators declared in the source.
+pub fn with_state<F, P, I, E>(mut f: F) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: UserState,
+    F: FnMut(&I::Data) -> P,
+    P: Parser<I, E>
+{
+    move |input: I| {
+        let mut parser = f(input.data());
+        parser.parse(input)
+    }
+}
+
+/// Runs `parser` unless it is already nested `limit` deep in other `limit_depth`s, in
+/// which case it fails with a committed error instead of going on to overflow the stack.
+/// Wrap the rule a grammar recurses through.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, State};
+/// use rtor::char::char;
+/// use rtor::combinator::{between, limit_depth, opt};
+///
+/// type Input<'a> = State<&'a str>;
+///
+/// fn nested(i: Input) -> ParseResult<usize, Input> {
+///     limit_depth(64, opt(between(char('['), nested, char(']'))).map(|n| n.map_or(0, |n| n + 1)))(i)
+/// }
+///
+/// assert_eq!(nested(State::new("[[[]]]")).unwrap().0, 3);
+///
+/// let deep = "[".repeat(100_000);
+/// let err = nested(State::new(&deep)).unwrap_err();
+/// assert_eq!((err.message.as_deref(), err.committed), (Some("nesting at most 64 deep"), true));
+/// assert_eq!(err.input.pos().column(), 65);
+/// ```
+pub fn limit_depth<P, I, E>(limit: usize, mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: Depth,
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    move |mut input: I| {
+        let depth = input.depth();
+        if depth >= limit {
+            return Err(E::expect(format!("nesting at most {} deep", limit), input).commit());
+        }
+        input.set_depth(depth + 1);
+        let (o, mut i) = parser.parse(input)?;
+        i.set_depth(depth);
+        Ok((o, i))
+    }
+}
+
+/// Charges `budget` one unit of fuel each time `parser` runs, failing with an
+/// [exhausted](ParseError::exhausted) error instead once the budget has run out. A
+/// [metering](crate::State::metering) state is charged by every alternative of an
+/// [`alt`] already, this is for other inputs, for the combinators that backtrack
+/// without one and for costing particular rules.
+pub fn metered<P, I, E>(budget: &Budget, mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    let budget = budget.clone();
+    move |input: I| {
+        if let Err(reason) = budget.charge(1) {
+            return Err(E::exhausted(reason, input));
+        }
+        parser.parse(input)
+    }
+}
+
+/// Builds a parser that refers to itself, for recursive rules that are made at runtime
+/// or capture values, where a named `fn` can't be used. `define` is given the parser
+/// being defined and may be called again for each level of nesting, see [`Recursive`].
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, Recursive, SimpleError};
+/// use rtor::char::char;
+/// use rtor::combinator::{between, many, recursive};
+///
+/// // nested lists with configurable brackets, counting the items
+/// fn list<'a>(open: char, close: char) -> Recursive<'a, &'a str, SimpleError<&'a str>, usize> {
+///     recursive(move |list| {
+///         between(char(open), many(list), char(close)).map(|items| items.iter().sum::<usize>() + 1)
+///     })
+/// }
+///
+/// let result: ParseResult<usize, &str> = list('<', '>').parse("<<><<>>>!");
+/// assert_eq!(result, Ok((4, "!")));
+/// ```
+pub fn recursive<'a, F, P, I, E>(define: F) -> Recursive<'a, I, E, P::Output>
+where
+    I: 'a,
+    E: 'a,
+    F: Fn(Recursive<'a, I, E, P::Output>) -> P + 'a,
+    P: Parser<I, E> + 'a,
+    P::Output: 'a
+{
+    let rule = Recursive::declare();
+    let this = rule.handle();
+    rule.define(move || define(this.clone()))
+}
+
+/// Runs `parser` at most once at each position of the input, reusing what it did the
+/// first time, successes and failures alike, whenever alternatives backtrack into it
+/// again there. This packrat parsing keeps heavily backtracking grammars linear.
+///
+/// Results go in `table`, which every use of the rule in the grammar shares and which
+/// only ever holds the results of this one rule. A cached success skips the parser
+/// altogether, so memoized rules must not depend on or change the user data.
+/// Incomplete and exhausted errors are not cached.
+/// # Example
+/// ```
+/// use std::cell::Cell;
+/// use rtor::{Parser, ParseResult, State, MemoTable};
+/// use rtor::char::{char, ascii::multi_digit1};
+/// use rtor::combinator::{alt, memo};
+///
+/// type Input<'a> = State<&'a str>;
+///
+/// let source = String::from("42-");
+/// let runs = Cell::new(0);
+/// let number = |i| -> ParseResult<u32, Input> {
+///     runs.set(runs.get() + 1);
+///     multi_digit1.map(|n: Input| n.parse().unwrap()).parse(i)
+/// };
+///
+/// let numbers = MemoTable::new();
+/// let mut parser = alt((
+///     (memo(&numbers, number), char('+')),
+///     (memo(&numbers, number), char('-')),
+/// ));
+///
+/// let ((n, op), _) = parser.parse(State::new(&source)).unwrap();
+/// assert_eq!((n, op, runs.get()), (42, '-', 1));
+///
+/// numbers.clear();
+/// assert!(numbers.is_empty());
+/// ```
+pub fn memo<'t, P, I, E>(table: &'t MemoTable<P::Output, E>, mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E> + 't
+where
+    I: Memoize,
+    E: ParseError<I> + Clone,
+    P: Parser<I, E> + 't,
+    P::Output: Clone
+{
+    move |input: I| {
+        match table.get(&input) {
+            Some(Ok((o, offset, end))) => return Ok((o, input.memo_skip(offset, end))),
+            Some(Err(e)) => return Err(e),
+            None => {}
+        }
+        match parser.parse(input.clone()) {
+            Ok((o, i)) => {
+                if let Some(offset) = input.offset_to(&i) {
+                    table.insert(&input, Ok((o.clone(), offset, i.location())));
+                }
+                Ok((o, i))
+            }
+            Err(e) => {
+                if e.needed().is_none() && e.exhaustion().is_none() {
+                    table.insert(&input, Err(e.clone()));
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
 pub fn alt<I, E, List>(mut list: List) -> impl FnMut(I) -> ParseResult<List::Output, I, E> 
 where 
     List: Alt<I, E>
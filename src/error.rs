@@ -1,39 +1,494 @@
 use std::{
-    fmt, 
+    fmt,
     error,
+    cmp::Ordering,
+};
+
+use crate::{
+    Input,
+    Location,
 };
 
 pub trait ParseError<I> {
     fn unexpect(input: I) -> Self;
     fn expect(message: String, input: I) -> Self;
+    fn expected(_item: Expected, input: I) -> Self where Self: Sized {
+        Self::unexpect(input)
+    }
     fn merge(self, other: Self) -> Self where Self: Sized{
         other
     }
+    /// Marks the error as committed, combinators that backtrack will pass it on instead
+    /// of trying another alternative. Error types that can't record it ignore the call.
+    fn commit(self) -> Self where Self: Sized {
+        self
+    }
+    fn is_committed(&self) -> bool {
+        false
+    }
+    /// Records that the error bubbled out of the grammar rule `label` which started at
+    /// `input`. Error types without a context stack, such as [`SimpleError`], ignore the
+    /// call, so [`context`](crate::Parser::context) has no effect on them.
+    fn add_context(self, _label: String, _input: I) -> Self where Self: Sized {
+        self
+    }
+    /// A parser ran out of a [partial](crate::Input::is_partial) input and could go on
+    /// with more data. Incomplete errors are committed, so no alternative is tried in
+    /// their place.
+    fn incomplete(_needed: Needed, input: I) -> Self where Self: Sized {
+        Self::unexpect(input).commit()
+    }
+    /// How much more data the parse needs, if the error is incomplete.
+    fn needed(&self) -> Option<Needed> {
+        None
+    }
+    /// The parse ran out of its [`Budget`](crate::Budget). Exhausted errors are
+    /// committed and no recovery is attempted, the parse is over.
+    fn exhausted(_reason: Exhausted, input: I) -> Self where Self: Sized {
+        Self::unexpect(input).commit()
+    }
+    /// Why the parse was stopped, if it ran out of its budget.
+    fn exhaustion(&self) -> Option<Exhausted> {
+        None
+    }
+    /// The input the error happened at, for error types that keep it.
+    fn input_mut(&mut self) -> Option<&mut I> {
+        None
+    }
+}
+
+/// A boxed error is an error, so grammars whose errors grow large, for instance with a
+/// [`State`](crate::State) input, can return `Box<E>` and keep their results small.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, ParseError, SimpleError, State};
+/// use rtor::char::char;
+///
+/// fn parser(i: State<&str>) -> ParseResult<char, State<&str>, Box<SimpleError<State<&str>>>> {
+///     char('a').or(char('b')).parse(i)
+/// }
+///
+/// let err = parser(State::new("c")).unwrap_err();
+/// assert_eq!((err.input.pos().column(), err.is_committed()), (1, false));
+/// ```
+impl<I, E> ParseError<I> for Box<E> where E: ParseError<I> {
+    fn unexpect(input: I) -> Self {
+        Box::new(E::unexpect(input))
+    }
+
+    fn expect(message: String, input: I) -> Self {
+        Box::new(E::expect(message, input))
+    }
+
+    fn expected(item: Expected, input: I) -> Self {
+        Box::new(E::expected(item, input))
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Box::new((*self).merge(*other))
+    }
+
+    fn commit(self) -> Self {
+        Box::new((*self).commit())
+    }
+
+    fn is_committed(&self) -> bool {
+        (**self).is_committed()
+    }
+
+    fn add_context(self, label: String, input: I) -> Self {
+        Box::new((*self).add_context(label, input))
+    }
+
+    fn incomplete(needed: Needed, input: I) -> Self {
+        Box::new(E::incomplete(needed, input))
+    }
+
+    fn needed(&self) -> Option<Needed> {
+        (**self).needed()
+    }
+
+    fn exhausted(reason: Exhausted, input: I) -> Self {
+        Box::new(E::exhausted(reason, input))
+    }
+
+    fn exhaustion(&self) -> Option<Exhausted> {
+        (**self).exhaustion()
+    }
+
+    fn input_mut(&mut self) -> Option<&mut I> {
+        (**self).input_mut()
+    }
+}
+
+/// How much more data a parser needs to go on, in bytes for text and in tokens otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Needed {
+    Unknown,
+    Size(usize),
+}
+
+impl fmt::Display for Needed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Needed::Unknown => f.write_str("more data"),
+            Needed::Size(n) => write!(f, "{} more", n),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// Which limit of a [`Budget`](crate::Budget) stopped a parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exhausted {
+    Fuel,
+    Cancelled,
+    Deadline,
+}
+
+impl fmt::Display for Exhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exhausted::Fuel => f.write_str("parse ran out of fuel"),
+            Exhausted::Cancelled => f.write_str("parse was cancelled"),
+            Exhausted::Deadline => f.write_str("parse ran past its deadline"),
+        }
+    }
+}
+
+/// Something a parser was looking for when it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    Char(char),
+    Str(String),
+    Label(String),
+    Eof,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Char(ch) => write!(f, "{:?}", ch),
+            Expected::Str(s) => write!(f, "{:?}", s),
+            Expected::Label(label) => f.write_str(label),
+            Expected::Eof => f.write_str("end of input"),
+        }
+    }
+}
+
+/// A grammar rule an error bubbled out of, added by [`Parser::context`](crate::Parser::context).
+///
+/// Frames form a chain from the outermost rule to the innermost one, which is also the
+/// order [`source`](error::Error::source) walks them in.
+/// # Example
+/// ```
+/// use std::error::Error;
+/// use rtor::{Parser, RichError, State};
+/// use rtor::char::char;
+///
+/// let mut parser = (char('['), (char('a'), char(']')).context("item")).context("list");
+/// let err: RichError<State<&str>> = parser.parse(State::new("[a)")).unwrap_err();
+/// let labels: Vec<_> = err.frames().map(|f| f.label.as_str()).collect();
+///
+/// assert_eq!(labels, ["list", "item"]);
+/// assert_eq!(err.source().unwrap().to_string(), "in list at 1:1");
+/// assert_eq!(err.to_string(), "expected ']', found ')' at 1:3\n  in list at 1:1\n  in item at 1:2");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame<L> {
+    pub label: String,
+    pub location: L,
+    inner: Option<Box<Frame<L>>>,
+}
+
+impl<L> Frame<L> {
+    pub fn inner(&self) -> Option<&Frame<L>> {
+        self.inner.as_deref()
+    }
+}
+
+impl<L> fmt::Display for Frame<L> where L: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "in {} at {}", self.label, self.location)
+    }
+}
+
+impl<L> error::Error for Frame<L> where L: fmt::Display + fmt::Debug + 'static {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.inner.as_deref().map(|f| f as &(dyn error::Error + 'static))
+    }
+}
+
+/// An error that only keeps where it happened and what was expected there, and whether
+/// it is committed, incomplete or exhausted, see [`ParseError`].
+#[derive(Debug, Clone, PartialEq)]
 pub struct SimpleError<I> {
     pub input: I,
-    pub message: Option<String>
+    pub message: Option<String>,
+    pub committed: bool,
+    pub needed: Option<Needed>,
+    pub exhausted: Option<Exhausted>,
 }
 
 impl<I> ParseError<I> for SimpleError<I> {
     fn unexpect(input: I) -> Self {
-        SimpleError { input, message: None }
+        SimpleError { input, message: None, committed: false, needed: None, exhausted: None }
     }
 
     fn expect(message: String, input: I) -> Self {
-        SimpleError { input, message: Some(message) }
+        SimpleError { input, message: Some(message), committed: false, needed: None, exhausted: None }
+    }
+
+    fn commit(self) -> Self {
+        SimpleError { committed: true, ..self }
+    }
+
+    fn is_committed(&self) -> bool {
+        self.committed
+    }
+
+    fn incomplete(needed: Needed, input: I) -> Self {
+        SimpleError { input, message: None, committed: true, needed: Some(needed), exhausted: None }
+    }
+
+    fn needed(&self) -> Option<Needed> {
+        self.needed
+    }
+
+    fn exhausted(reason: Exhausted, input: I) -> Self {
+        SimpleError { input, message: None, committed: true, needed: None, exhausted: Some(reason) }
+    }
+
+    fn exhaustion(&self) -> Option<Exhausted> {
+        self.exhausted
+    }
+
+    fn input_mut(&mut self) -> Option<&mut I> {
+        Some(&mut self.input)
     }
 }
 
 impl<I> fmt::Display for SimpleError<I> where I: fmt::Display {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+       if let Some(needed) = self.needed {
+           return write!(f, "incomplete input, needed {}", needed);
+       }
+       if let Some(reason) = self.exhausted {
+           return write!(f, "{}", reason);
+       }
        match self.message {
            Some(ref msg) => write!(f, "expected {}, but found {}", msg, self.input),
            None => write!(f, "unexpected {}", self.input)
-       }      
+       }
     }
 }
 
-impl<T> error::Error for SimpleError<T> where T: fmt::Display + fmt::Debug {}
\ No newline at end of file
+impl<T> error::Error for SimpleError<T> where T: fmt::Display + fmt::Debug {}
+
+/// An error that remembers where it happened and everything that was expected there.
+///
+/// Merging two `RichError`s keeps the one that got further into the input, if both
+/// stopped at the same place their expected items are combined, the result is committed
+/// if either was and keeps the rules of the first, or of the second if the first has
+/// none. Errors in different
+/// sources can't be ranked, the later one is kept.
+/// # Example
+/// ```
+/// use rtor::{Parser, RichError, State};
+/// use rtor::char::char;
+/// use rtor::combinator::alt;
+///
+/// let mut parser = (char('a'), alt((char('b'), char('c'))));
+/// let err: RichError<State<&str>> = parser.parse(State::new("ad")).unwrap_err();
+///
+/// assert_eq!(err.to_string(), "expected one of 'b', 'c', found 'd' at 1:2");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichError<I: Location> {
+    pub input: I,
+    pub expected: Vec<Expected>,
+    pub committed: bool,
+    pub needed: Option<Needed>,
+    pub exhausted: Option<Exhausted>,
+    pub context: Option<Box<Frame<I::Location>>>,
+}
+
+impl<I> RichError<I> where I: Location {
+    fn new(input: I, expected: Vec<Expected>) -> Self {
+        RichError { input, expected, committed: false, needed: None, exhausted: None, context: None }
+    }
+
+    /// The rules the error bubbled out of, outermost first.
+    pub fn frames(&self) -> impl Iterator<Item = &Frame<I::Location>> {
+        let mut frame = self.context.as_deref();
+        std::iter::from_fn(move || {
+            let current = frame?;
+            frame = current.inner();
+            Some(current)
+        })
+    }
+}
+
+impl<I> RichError<I> where I: Input + Location {
+    pub fn location(&self) -> I::Location {
+        self.input.location()
+    }
+
+    pub fn found(&self) -> Option<I::Token> {
+        self.input.clone().peek()
+    }
+}
+
+impl<I> ParseError<I> for RichError<I> where I: Location {
+    fn unexpect(input: I) -> Self {
+        RichError::new(input, vec![])
+    }
+
+    fn expect(message: String, input: I) -> Self {
+        RichError::new(input, vec![Expected::Label(message)])
+    }
+
+    fn expected(item: Expected, input: I) -> Self {
+        RichError::new(input, vec![item])
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        match self.input.progress(&other.input) {
+            Some(Ordering::Less) | None => other,
+            Some(Ordering::Greater) => self,
+            Some(Ordering::Equal) => {
+                for item in other.expected {
+                    if !self.expected.contains(&item) {
+                        self.expected.push(item);
+                    }
+                }
+                self.committed |= other.committed;
+                if self.context.is_none() {
+                    self.context = other.context;
+                }
+                self
+            }
+        }
+    }
+
+    fn commit(self) -> Self {
+        RichError { committed: true, ..self }
+    }
+
+    fn is_committed(&self) -> bool {
+        self.committed
+    }
+
+    fn incomplete(needed: Needed, input: I) -> Self {
+        RichError { committed: true, needed: Some(needed), ..RichError::new(input, vec![]) }
+    }
+
+    fn needed(&self) -> Option<Needed> {
+        self.needed
+    }
+
+    fn exhausted(reason: Exhausted, input: I) -> Self {
+        RichError { committed: true, exhausted: Some(reason), ..RichError::new(input, vec![]) }
+    }
+
+    fn exhaustion(&self) -> Option<Exhausted> {
+        self.exhausted
+    }
+
+    fn input_mut(&mut self) -> Option<&mut I> {
+        Some(&mut self.input)
+    }
+
+    fn add_context(mut self, label: String, input: I) -> Self {
+        let inner = self.context.take();
+        self.context = Some(Box::new(Frame { label, location: input.location(), inner }));
+        self
+    }
+}
+
+impl<I> fmt::Display for RichError<I>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_message(f, true)?;
+        for frame in self.frames() {
+            write!(f, "\n  {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> RichError<I>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: fmt::Display
+{
+    pub(crate) fn fmt_message(&self, f: &mut fmt::Formatter<'_>, located: bool) -> fmt::Result {
+        if let Some(needed) = self.needed {
+            write!(f, "incomplete input, needed {}", needed)?;
+        } else if let Some(reason) = self.exhausted {
+            write!(f, "{}", reason)?;
+        } else {
+            let found = match self.found() {
+                Some(t) => format!("{:?}", t),
+                None => "end of input".to_owned()
+            };
+            match self.expected.split_first() {
+                None => write!(f, "unexpected {}", found)?,
+                Some((first, [])) => write!(f, "expected {}, found {}", first, found)?,
+                Some((first, rest)) => {
+                    write!(f, "expected one of {}", first)?;
+                    for item in rest {
+                        write!(f, ", {}", item)?;
+                    }
+                    write!(f, ", found {}", found)?;
+                }
+            }
+        }
+        // the location comes last, as in a recovered error's message
+        if located {
+            write!(f, " at {}", self.location())?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> error::Error for RichError<I>
+where
+    I: Input + Location + fmt::Debug,
+    I::Token: fmt::Debug,
+    I::Location: fmt::Display + fmt::Debug + 'static
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.context.as_deref().map(|f| f as &(dyn error::Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::State;
+
+    type Error<'a> = RichError<State<&'a str>>;
+
+    #[test]
+    fn merging_at_the_same_place_keeps_commitment_and_context() {
+        let input = State::new("x");
+        let plain = Error::expected(Expected::Char('a'), input.clone());
+        let labelled = Error::expected(Expected::Char('b'), input.clone())
+            .add_context("rule".to_owned(), input)
+            .commit();
+
+        let merged = plain.clone().merge(labelled.clone());
+        assert_eq!(merged.expected, [Expected::Char('a'), Expected::Char('b')]);
+        assert!(merged.is_committed());
+        assert_eq!(merged.frames().map(|f| f.label.as_str()).collect::<Vec<_>>(), ["rule"]);
+
+        let merged = labelled.merge(plain);
+        assert!(merged.is_committed());
+        assert_eq!(merged.frames().count(), 1);
+    }
+}
@@ -0,0 +1,306 @@
+//! Parsing layout, where indentation rather than brackets shows how lines nest.
+//!
+//! An input keeps a reference position, set by [`with_pos`] and the combinators built on
+//! it, and the other combinators compare the current position with it. Since the
+//! reference travels with the input, nested blocks form a stack that unwinds as they
+//! end and when alternatives backtrack.
+//!
+//! Columns are those of the [`State`](crate::State)'s tracking policy, so they are
+//! meaningless with [`Offset`](crate::Offset). A check that fails reports what layout
+//! it wanted and the unexpected indentation it found instead.
+//! # Example
+//! ```
+//! use rtor::{Parser, ParseResult, State};
+//! use rtor::char::ascii::{multi_alpha1, multi_space};
+//! use rtor::combinator::opt;
+//! use rtor::indent::{block, indented};
+//!
+//! type Input<'a> = State<&'a str>;
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Node(String, Vec<Node>);
+//!
+//! fn node(i: Input) -> ParseResult<Node, Input> {
+//!     (multi_alpha1.andl(multi_space), opt(indented(block(node))))
+//!         .map(|(name, children): (Input, _)| Node(name.to_string(), children.unwrap_or_default()))
+//!         .parse(i)
+//! }
+//!
+//! let (tree, _) = block(node)(State::new("a\n  b\n  c\n    d\ne\n")).unwrap();
+//! assert_eq!(tree, [
+//!     Node("a".into(), vec![
+//!         Node("b".into(), vec![]),
+//!         Node("c".into(), vec![Node("d".into(), vec![])]),
+//!     ]),
+//!     Node("e".into(), vec![]),
+//! ]);
+//!
+//! let err = block(node)(State::new("a\n  b\n c\n")).unwrap_err();
+//! assert_eq!(err.message.as_deref(), Some("end of the block at column 1 or indentation at column 3 (unexpected indentation at 3:2)"));
+//! ```
+
+use crate::{
+    Parser,
+    Input,
+    Location,
+    ParseResult,
+    ParseError,
+    Pos,
+};
+
+/// An input that keeps a reference position for layout.
+pub trait Layout: Location<Location = Pos> {
+    fn reference(&self) -> Pos;
+
+    fn set_reference(&mut self, pos: Pos);
+}
+
+/// The error for input laid out other than as `wanted`.
+fn unexpected<I, E>(wanted: String, input: I) -> E where I: Layout, E: ParseError<I> {
+    let message = format!("{} (unexpected indentation at {})", wanted, input.location());
+    E::expect(message, input)
+}
+
+/// Runs `parser` with the reference set to where it starts, and puts the old reference
+/// back afterwards.
+pub fn with_pos<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: Layout,
+    P: Parser<I, E>
+{
+    move |mut input: I| {
+        let reference = input.reference();
+        input.set_reference(input.location());
+        let (o, mut i) = parser.parse(input)?;
+        i.set_reference(reference);
+        Ok((o, i))
+    }
+}
+
+/// Runs `parser` if the input is indented further than the reference.
+pub fn indented<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: Layout,
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    move |input: I| {
+        let column = input.reference().column();
+        if input.location().column() <= column {
+            return Err(unexpected(format!("indentation past column {}", column), input));
+        }
+        parser.parse(input)
+    }
+}
+
+/// Runs `parser` if the input is at the same column as the reference.
+pub fn align<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: Layout,
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    move |input: I| {
+        let column = input.reference().column();
+        if input.location().column() != column {
+            return Err(unexpected(format!("indentation at column {}", column), input));
+        }
+        parser.parse(input)
+    }
+}
+
+/// Runs `parser` if the input is still on the line of the reference.
+pub fn same_line<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: Layout,
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    move |input: I| {
+        let line = input.reference().line();
+        if input.location().line() != line {
+            return Err(unexpected(format!("continuation of line {}", line), input));
+        }
+        parser.parse(input)
+    }
+}
+
+/// One or more `parser`s all starting at the column of the first one.
+///
+/// Fails with a committed error when the next line is indented less than the first item
+/// but more than the enclosing reference, since such a line belongs to no block, and
+/// when an item consumes nothing, since the block would never end.
+pub fn block<P, I, E>(mut parser: P) -> impl FnMut(I) -> ParseResult<Vec<P::Output>, I, E>
+where
+    I: Layout + Input,
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    move |mut input: I| {
+        let outer = input.reference();
+        let start = input.location();
+        input.set_reference(start);
+        let (o, mut input) = item(&mut parser, input)?;
+        let mut result = vec![o];
+        loop {
+            let column = input.location().column();
+            if column != start.column() || input.clone().peek().is_none() {
+                if column > outer.column() && column < start.column() {
+                    let message = format!("end of the block at column {} or indentation at column {}", outer.column(), start.column());
+                    return Err(unexpected::<I, E>(message, input).commit());
+                }
+                break;
+            }
+            let (o, i) = item(&mut parser, input)?;
+            result.push(o);
+            input = i;
+        }
+        input.set_reference(outer);
+        Ok((result, input))
+    }
+}
+
+fn item<P, I, E>(parser: &mut P, input: I) -> ParseResult<P::Output, I, E>
+where
+    I: Layout + Input,
+    E: ParseError<I>,
+    P: Parser<I, E>
+{
+    let start = input.location();
+    let (o, i) = parser.parse(input)?;
+    if i.location() == start {
+        return Err(E::expect("a block item that consumes input".to_owned(), i).commit());
+    }
+    Ok((o, i))
+}
+
+/// Runs `parser` as one logical line that may go on over following lines, as long as
+/// they are indented past where it started. Use [`fold_space`] between its parts.
+pub fn line_fold<P, I, E>(parser: P) -> impl FnMut(I) -> ParseResult<P::Output, I, E>
+where
+    I: Layout,
+    P: Parser<I, E>
+{
+    with_pos(parser)
+}
+
+/// Skips `space` inside a [`line_fold`], failing if it ends on a later line that is not
+/// indented past the start of the fold.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, State};
+/// use rtor::char::ascii::{multi_alpha1, multi_space};
+/// use rtor::combinator::sep_by1;
+/// use rtor::indent::{fold_space, line_fold};
+///
+/// type Input<'a> = State<&'a str>;
+///
+/// fn words(i: Input) -> ParseResult<Vec<Input>, Input> {
+///     line_fold(sep_by1(multi_alpha1, fold_space(multi_space))).parse(i)
+/// }
+///
+/// assert_eq!(words(State::new("a b\n  c\nd")).unwrap().0.len(), 3);
+/// ```
+pub fn fold_space<S, I, E>(mut space: S) -> impl FnMut(I) -> ParseResult<S::Output, I, E>
+where
+    I: Layout,
+    E: ParseError<I>,
+    S: Parser<I, E>
+{
+    move |input: I| {
+        let line = input.location().line();
+        let (o, i) = space.parse(input)?;
+        let column = i.reference().column();
+        if i.location().line() != line && i.location().column() <= column {
+            return Err(unexpected(format!("indentation past column {}", column), i));
+        }
+        Ok((o, i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SimpleError, State};
+    use crate::char::{char, ascii::{multi_alpha1, multi_space}};
+    use crate::combinator::{pure, sep_by1};
+
+    type Text<'a> = State<&'a str>;
+    type Result<'a, O> = ParseResult<O, Text<'a>, Box<SimpleError<Text<'a>>>>;
+
+    fn word(i: Text) -> Result<Text> {
+        multi_alpha1.andl(multi_space).parse(i)
+    }
+
+    fn message<O>(result: Result<O>) -> String {
+        result.err().unwrap().message.unwrap()
+    }
+
+    #[test]
+    fn with_pos_restores_the_reference() {
+        fn reference(i: Text) -> Result<Pos> {
+            Ok((i.reference(), i))
+        }
+
+        let input = word(State::new("a b")).unwrap().1;
+        let (inner, rest) = with_pos(char('b').andr(reference)).parse(input).unwrap();
+        assert_eq!(inner.column(), 3);
+        assert_eq!(rest.reference().column(), 1);
+    }
+
+    #[test]
+    fn indented_needs_a_deeper_column() {
+        let input = word(State::new("a\n  b\nc")).unwrap().1;
+        let (b, rest) = indented(word).parse(input).unwrap();
+        assert_eq!(*b, "b");
+        assert_eq!(message(indented(word).parse(rest)), "indentation past column 1 (unexpected indentation at 3:1)");
+    }
+
+    #[test]
+    fn align_needs_the_same_column() {
+        let input = word(State::new("a\nb\n c")).unwrap().1;
+        let (b, rest) = align(word).parse(input).unwrap();
+        assert_eq!(*b, "b");
+        assert_eq!(message(align(word).parse(rest)), "indentation at column 1 (unexpected indentation at 3:2)");
+    }
+
+    #[test]
+    fn same_line_needs_the_same_line() {
+        let mut parser = with_pos((word, same_line(word)));
+        assert!(parser.parse(State::new("a b")).is_ok());
+        assert_eq!(message(parser.parse(State::new("a\nb"))), "continuation of line 1 (unexpected indentation at 2:1)");
+    }
+
+    #[test]
+    fn block_collects_aligned_items() {
+        let (items, rest) = block(word).parse(State::new("a\nb\n  c")).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(*rest, "c");
+    }
+
+    #[test]
+    fn block_rejects_lines_between_columns() {
+        let input = State::new("a\n    b\n  c");
+        let result = (word, indented(block(word))).parse(input);
+        let err = result.err().unwrap();
+        assert!(err.is_committed());
+        assert_eq!(err.message.as_deref(), Some("end of the block at column 1 or indentation at column 5 (unexpected indentation at 3:3)"));
+    }
+
+    #[test]
+    fn block_rejects_items_that_consume_nothing() {
+        let result: Result<Vec<()>> = block(pure(())).parse(State::new("a"));
+        let err = result.err().unwrap();
+        assert!(err.is_committed());
+        assert_eq!(err.message.as_deref(), Some("a block item that consumes input"));
+    }
+
+    #[test]
+    fn fold_space_continues_on_deeper_lines() {
+        let mut words = line_fold(sep_by1(multi_alpha1, fold_space(multi_space)));
+        let result: Result<Vec<Text>> = words.parse(State::new("a\n b\nc"));
+        let (words, rest) = result.unwrap();
+        assert_eq!(words.len(), 2);
+        assert_eq!(*rest, "\nc");
+    }
+}
@@ -2,6 +2,12 @@ use std::{
     str::Chars, 
     slice::Iter, 
     iter::Cloned, 
+    cmp::Ordering,
+};
+
+use crate::{
+    AsChar,
+    Exhausted,
 };
 
 pub trait Input: Clone {
@@ -12,9 +18,107 @@ pub trait Input: Clone {
 
     fn peek(&mut self) -> Option<Self::Token>;
 
+    /// What was consumed between this input and `other`, a later position of it. Must not
+    /// panic when `other` is something else, [`offset_to`](Input::offset_to) tells the
+    /// two cases apart.
     fn diff(&self, other: &Self) -> Self;
 
     fn tokens(&self) -> Self::Tokens;
+
+    /// How far `other` is from this input, or `None` if it isn't a later position of it.
+    /// Counts tokens unless the input has a better measure, `&str` counts bytes.
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        Some(self.diff(other).tokens().count())
+    }
+
+    /// Whether more data may follow what the input holds now, in which case running out
+    /// of it is [incomplete](crate::ParseError::incomplete) rather than the end of input.
+    fn is_partial(&self) -> bool {
+        false
+    }
+
+    /// If the input starts with `literal`, the offset just past it, in the unit of
+    /// [`offset_to`](Input::offset_to).
+    fn starts_with(&self, literal: &str) -> Option<usize> where Self::Token: AsChar {
+        let mut tokens = self.tokens();
+        let mut n = 0;
+        for ch in literal.chars() {
+            match tokens.next() {
+                Some(t) if t.as_char() == ch => n += 1,
+                _ => return None
+            }
+        }
+        Some(n)
+    }
+
+    /// Splits the input at `offset`, in the unit of [`offset_to`](Input::offset_to), into
+    /// what comes before it, as `diff` would return it, and the rest. An offset inside a
+    /// token splits before it, and one past the end at the end. Counts tokens unless
+    /// overridden, inputs with their own `offset_to` should override this too.
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let mut rest = self.clone();
+        for _ in 0..offset {
+            if rest.next().is_none() {
+                break;
+            }
+        }
+        (self.diff(&rest), rest)
+    }
+
+    /// Splits the input before the first token matching `pred`, or at the end if none
+    /// does.
+    /// # Example
+    /// ```
+    /// use rtor::{Input, State};
+    ///
+    /// let (word, rest) = State::new("hello\nworld").split_at_position(|ch| *ch == 'o');
+    /// assert_eq!((*word, *rest), ("hell", "o\nworld"));
+    /// assert_eq!(rest.pos().column(), 5);
+    /// ```
+    fn split_at_position<P>(&self, mut pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let mut rest = self.clone();
+        while let Some(t) = rest.peek() {
+            if pred(&t) {
+                break;
+            }
+            rest.next();
+        }
+        (self.diff(&rest), rest)
+    }
+
+    /// The offset of the first token matching `pred`.
+    fn position<P>(&self, pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        let (_, mut rest) = self.split_at_position(pred);
+        rest.peek().and_then(|_| self.offset_to(&rest))
+    }
+
+    /// How many bytes the tokens would take up as UTF-8. Walks them unless overridden.
+    fn len_utf8(&self) -> usize where Self::Token: AsChar {
+        self.tokens().map(|t| t.len_utf8()).sum()
+    }
+
+    /// Takes `cost` from the [`Budget`](crate::Budget) the input carries, if it carries
+    /// one. [`alt`](crate::combinator::alt) charges one for each alternative it tries.
+    fn charge(&self, _cost: usize) -> Result<(), Exhausted> {
+        Ok(())
+    }
+}
+
+/// An input that knows where in the source it is.
+///
+/// Two inputs over the same source compare by how far they got, see
+/// [`progress`](Location::progress), which is what [`RichError`](crate::RichError) uses
+/// to keep the furthest failure.
+pub trait Location {
+    type Location: Ord + Clone;
+
+    fn location(&self) -> Self::Location;
+
+    /// Whether this input got less, as or further than `other`, or `None` if they are
+    /// in different sources and there is no telling.
+    fn progress(&self, other: &Self) -> Option<Ordering> {
+        Some(self.location().cmp(&other.location()))
+    }
 }
 
 impl<'a> Input for &'a str {
@@ -33,13 +137,42 @@ impl<'a> Input for &'a str {
     }
 
     fn diff(&self, other: &Self) -> Self {
-        let offset = other.as_ptr() as usize - self.as_ptr() as usize;
-        &self[..offset]
+        &self[..self.offset_to(other).unwrap_or(0)]
     }
 
     fn tokens(&self) -> Self::Tokens {
         self.chars()
     }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        let offset = (other.as_ptr() as usize).checked_sub(self.as_ptr() as usize)?;
+        self.is_char_boundary(offset).then_some(offset)
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> {
+        str::starts_with(self, literal).then_some(literal.len())
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let mut offset = offset.min(self.len());
+        while !self.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        str::split_at(self, offset)
+    }
+
+    fn split_at_position<P>(&self, mut pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let offset = self.find(|ch| pred(&ch)).unwrap_or(self.len());
+        str::split_at(self, offset)
+    }
+
+    fn position<P>(&self, mut pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        self.find(|ch| pred(&ch))
+    }
+
+    fn len_utf8(&self) -> usize {
+        self.len()
+    }
 }
 
 impl<'a, T: Clone> Input for &'a [T] {
@@ -58,11 +191,33 @@ impl<'a, T: Clone> Input for &'a [T] {
     }
     
     fn diff(&self, other: &Self) -> Self {
-        let offset = other.as_ptr() as usize - self.as_ptr() as usize;
-        &self[..offset]
+        &self[..self.offset_to(other).unwrap_or(0)]
     }
 
     fn tokens(&self) -> Self::Tokens {
         self.iter().cloned()
     }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        let size = std::mem::size_of::<T>().max(1);
+        let bytes = (other.as_ptr() as usize).checked_sub(self.as_ptr() as usize)?;
+        (bytes % size == 0 && bytes / size <= self.len()).then_some(bytes / size)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        <[T]>::split_at(self, offset.min(self.len()))
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> where T: AsChar {
+        T::prefix(self, literal)
+    }
+
+    fn split_at_position<P>(&self, pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let offset = self.iter().position(pred).unwrap_or(self.len());
+        <[T]>::split_at(self, offset)
+    }
+
+    fn position<P>(&self, pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        self.iter().position(pred)
+    }
 }
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use crate::{
+    Input,
+    Location,
+    replay::{Replay, Tokens},
+};
+
+/// An input over the items of an [`Iterator`], such as the output of a tokenizer.
+///
+/// Items are pulled from the iterator only when a parser looks at them and are kept in a
+/// buffer shared by all clones of the input, so backtracking replays them instead of
+/// needing the iterator again. The buffer only keeps items from the oldest clone still
+/// alive onwards. `diff` gives back the items consumed in between. Its location is the
+/// index of the next item.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, IterInput};
+/// use rtor::token::{token, satisfy};
+/// use rtor::combinator::{alt, many1};
+///
+/// type Input = IterInput<std::vec::IntoIter<i32>>;
+///
+/// fn parser(i: Input) -> ParseResult<Vec<i32>, Input> {
+///     many1(alt((token(0).andr(token(1)), satisfy(|n: &i32| *n > 1)))).parse(i)
+/// }
+///
+/// let (output, rest) = parser(IterInput::new(vec![0, 1, 5, 0, 7])).unwrap();
+/// assert_eq!(output, [1, 5]);
+/// assert_eq!(rest.to_vec(), [0, 7]);
+/// ```
+pub struct IterInput<It: Iterator> {
+    replay: Replay<It>,
+}
+
+impl<It> IterInput<It> where It: Iterator {
+    pub fn new<T>(iter: T) -> Self where T: IntoIterator<IntoIter = It> {
+        Self {
+            replay: Replay::new(iter.into_iter()),
+        }
+    }
+
+    /// How many items the buffer is holding on to right now.
+    pub fn buffered(&self) -> usize {
+        self.replay.buffered()
+    }
+}
+
+impl<It> IterInput<It> where It: Iterator, It::Item: Clone {
+    /// The remaining items, draining the iterator for an unbounded input.
+    pub fn to_vec(&self) -> Vec<It::Item> {
+        self.tokens().collect()
+    }
+}
+
+impl<It> Clone for IterInput<It> where It: Iterator {
+    fn clone(&self) -> Self {
+        Self { replay: self.replay.clone() }
+    }
+}
+
+impl<It> fmt::Debug for IterInput<It> where It: Iterator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IterInput").field(&self.replay).finish()
+    }
+}
+
+impl<It> Input for IterInput<It> where It: Iterator, It::Item: Clone {
+    type Token = It::Item;
+    type Tokens = Tokens<It>;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        self.replay.next()
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        self.replay.peek()
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        Self { replay: self.replay.diff(&other.replay) }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        Tokens::new(self.replay.clone())
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        self.replay.offset_to(&other.replay)
+    }
+}
+
+impl<It> Location for IterInput<It> where It: Iterator {
+    type Location = usize;
+
+    fn location(&self) -> Self::Location {
+        self.replay.pos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    /// Counts the items pulled from `0..n`.
+    fn counted(n: u32) -> (IterInput<impl Iterator<Item = u32>>, Rc<Cell<usize>>) {
+        let pulled = Rc::new(Cell::new(0));
+        let count = pulled.clone();
+        let iter = (0..n).inspect(move |_| count.set(count.get() + 1));
+        (IterInput::new(iter), pulled)
+    }
+
+    #[test]
+    fn items_are_pulled_on_demand() {
+        let (mut input, pulled) = counted(100);
+        assert_eq!(pulled.get(), 0);
+        assert_eq!(input.peek(), Some(0));
+        assert_eq!(input.next(), Some(0));
+        assert_eq!(pulled.get(), 1);
+    }
+
+    #[test]
+    fn buffer_is_trimmed_to_oldest_checkpoint() {
+        let (mut input, _) = counted(100);
+        let checkpoint = input.clone();
+        for _ in 0..50 {
+            input.next();
+        }
+        assert_eq!(input.buffered(), 50);
+
+        let copy = input.clone();
+        assert_eq!(input.buffered(), 50);
+
+        drop(checkpoint);
+        assert_eq!(input.buffered(), 0);
+        assert_eq!(copy.to_vec().len(), 50);
+    }
+
+    #[test]
+    fn diff_replays_consumed_items() {
+        let (mut input, pulled) = counted(10);
+        let start = input.clone();
+        for _ in 0..4 {
+            input.next();
+        }
+        assert_eq!(start.diff(&input).to_vec(), [0, 1, 2, 3]);
+        assert_eq!(input.to_vec(), [4, 5, 6, 7, 8, 9]);
+        assert_eq!(pulled.get(), 10);
+    }
+}
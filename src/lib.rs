@@ -2,32 +2,158 @@ mod parser;
 mod error;
 pub mod char;
 pub mod combinator;
+pub mod recover;
+pub mod token;
+pub mod indent;
+pub mod pratt;
 mod input;
 mod state;
+mod located;
+mod partial;
+mod read;
+mod shared;
+mod chunked;
+mod iter;
+mod replay;
+mod line_index;
+mod report;
+mod source;
+mod stack;
+mod budget;
+mod recursive;
+mod memo;
+mod scope;
 
 pub use self::{
     error::{
         SimpleError,
+        RichError,
+        Expected,
+        Frame,
+        Needed,
+        Exhausted,
         ParseError
     },
-    parser::Parser,
-    input::Input,
+    parser::{
+        Parser,
+        BoxedParser
+    },
+    input::{
+        Input,
+        Location
+    },
     state::{
         State,
-        Pos
-    }
+        StateData,
+        Pos,
+        Track,
+        UserState,
+        Depth,
+        PerToken,
+        Offset,
+        Editor,
+        Utf16
+    },
+    located::Located,
+    partial::Partial,
+    read::ReadInput,
+    shared::{
+        Shared,
+        Slice
+    },
+    chunked::Chunked,
+    iter::IterInput,
+    line_index::LineIndex,
+    report::{
+        Report,
+        Diagnostic,
+        SourceSpan
+    },
+    source::{
+        SourceId,
+        Sources
+    },
+    stack::InputStack,
+    budget::Budget,
+    recursive::{
+        Recursive,
+        Declared
+    },
+    memo::{
+        MemoTable,
+        Memoize
+    },
+    scope::Scope
 };
 
 pub type ParseResult<O, I, E = SimpleError<I>> = Result<(O, I), E>;
 
 pub trait AsChar {
     fn as_char(&self) -> char;
+
+    /// How many bytes the token takes up in UTF-8 source.
+    fn len_utf8(&self) -> usize {
+        self.as_char().len_utf8()
+    }
+
+    /// How many code points the token starts, none for a byte continuing a UTF-8 sequence.
+    fn len_chars(&self) -> usize {
+        1
+    }
+
+    /// How many UTF-16 code units the code point the token starts takes up.
+    fn len_utf16(&self) -> usize {
+        self.as_char().len_utf16()
+    }
+
+    /// How many of `tokens` spell out `literal` at their start, if they do. Compares a
+    /// token at a time unless overridden.
+    fn prefix(tokens: &[Self], literal: &str) -> Option<usize> where Self: Sized {
+        prefix_by_char(tokens, literal)
+    }
+}
+
+fn prefix_by_char<T: AsChar>(tokens: &[T], literal: &str) -> Option<usize> {
+    let mut tokens = tokens.iter();
+    let mut n = 0;
+    for ch in literal.chars() {
+        match tokens.next() {
+            Some(t) if t.as_char() == ch => n += 1,
+            _ => return None
+        }
+    }
+    Some(n)
 }
 
 impl AsChar for u8 {
     fn as_char(&self) -> char {
         *self as char
     }
+
+    fn len_utf8(&self) -> usize {
+        1
+    }
+
+    fn len_chars(&self) -> usize {
+        if self & 0xC0 == 0x80 { 0 } else { 1 }
+    }
+
+    fn len_utf16(&self) -> usize {
+        match self {
+            0x80..=0xBF => 0,
+            0xF0..=0xFF => 2,
+            _ => 1
+        }
+    }
+
+    fn prefix(tokens: &[Self], literal: &str) -> Option<usize> {
+        // an ASCII literal is the same bytes either way, others are compared as chars
+        if literal.is_ascii() {
+            tokens.starts_with(literal.as_bytes()).then_some(literal.len())
+        } else {
+            prefix_by_char(tokens, literal)
+        }
+    }
 }
 
 impl AsChar for char {
@@ -41,13 +167,13 @@ pub trait FindToken<T> {
     fn find_token(&self, token: &T) -> bool;
 }
 
-impl<'a> FindToken<char> for &'a str {
+impl FindToken<char> for &str {
     fn find_token(&self, token: &char) -> bool {
         self.chars().any(|x| x == *token)
     }
 }
 
-impl<'a> FindToken<u8> for &'a str {
+impl FindToken<u8> for &str {
     fn find_token(&self, token: &u8) -> bool {
         self.chars().any(|x| x == *token as char)
     }
@@ -59,13 +185,13 @@ impl<T: PartialEq, const N: usize> FindToken<T> for [T; N] {
     }
 }
 
-impl<'a, T: PartialEq, const N: usize> FindToken<T> for &'a [T; N] {
+impl<T: PartialEq, const N: usize> FindToken<T> for &[T; N] {
     fn find_token(&self, token: &T) -> bool {
         self.iter().any(|x| x == token)
     }
 }
 
-impl<'a, T: PartialEq> FindToken<T> for &'a [T] {
+impl<T: PartialEq> FindToken<T> for &[T] {
     fn find_token(&self, token: &T) -> bool {
         self.iter().any(|x| x == token)
     }
@@ -238,27 +364,39 @@ macro_rules! alt_parser_impl {
 }
 
 macro_rules! alt_parser_inner {
-    ($field:tt, (), $self:expr, $input:expr, $a:ident, $($rest:ident),*) => {
+    ($field:tt, (), $self:expr, $input:expr, $a:ident, $($rest:ident),*) => {{
+        if let Err(reason) = $input.charge(1) {
+            return Err(ParseError::exhausted(reason, $input));
+        }
         match $self.$field.parse($input.clone()) {
             Ok(t) => Ok(t),
+            Err(e1) if e1.is_committed() => Err(e1),
             Err(e1) => succ_alt_parser_inner!($field, (e1), $self, $input, $($rest),*)
         }
-    };
-    ($field:tt, ($err:expr), $self:expr, $input:expr, $a:ident, $($rest:ident),*) => {
+    }};
+    ($field:tt, ($err:expr), $self:expr, $input:expr, $a:ident, $($rest:ident),*) => {{
+        if let Err(reason) = $input.charge(1) {
+            return Err(ParseError::exhausted(reason, $input));
+        }
         match $self.$field.parse($input.clone()) {
             Ok(t) => Ok(t),
+            Err(e2) if e2.is_committed() => Err(e2),
             Err(e2) => {
                 let e1 = $err.merge(e2);
                 succ_alt_parser_inner!($field, (e1), $self, $input, $($rest),*)
             }
         }
-    };
-    ($field:tt, ($err:expr), $self:expr, $input:expr, $a:ident) => { 
+    }};
+    ($field:tt, ($err:expr), $self:expr, $input:expr, $a:ident) => {{
+        if let Err(reason) = $input.charge(1) {
+            return Err(ParseError::exhausted(reason, $input));
+        }
         match $self.$field.parse($input.clone()) {
             Ok(t) => Ok(t),
+            Err(e2) if e2.is_committed() => Err(e2),
             Err(e2) => Err($err.merge(e2))
         }
-    }
+    }}
 }
 
 macro_rules! succ_alt_parser_inner {
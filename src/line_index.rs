@@ -0,0 +1,100 @@
+use crate::Pos;
+
+/// The start of every line in a source, built once so byte offsets can be turned into
+/// lines and columns with a binary search instead of tracking them token by token.
+///
+/// Columns count code points, like the default [`PerToken`](crate::PerToken) policy.
+/// # Example
+/// ```
+/// use rtor::{Parser, RichError, State, Offset, LineIndex};
+/// use rtor::char::{char, string};
+///
+/// let source = "let a;\nlet é = b";
+/// let mut parser = (string("let a;\nlet é = "), char('1'));
+///
+/// let err: RichError<State<&str, (), Offset>> = parser.parse(State::new(source).tracking(Offset)).unwrap_err();
+/// assert_eq!(err.location().to_string(), "offset 16");
+///
+/// let index = LineIndex::new(source);
+/// assert_eq!(index.resolve(err.location()).to_string(), "2:9");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The text of line `line`, counting from 1, without its line break, `"\n"` or
+    /// `"\r\n"`.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.starts.get(line.checked_sub(1)?)?;
+        match self.starts.get(line) {
+            Some(next) => {
+                let text = &self.source[start..next - 1];
+                Some(text.strip_suffix('\r').unwrap_or(text))
+            }
+            None => Some(&self.source[start..])
+        }
+    }
+
+    /// The position of the byte `offset`, which is moved back to the start of its char if
+    /// it falls inside one and to the end of the source if it is past it.
+    pub fn pos(&self, offset: usize) -> Pos {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let start = self.starts[line - 1];
+        let column = self.source[start..offset].chars().count() + 1;
+        Pos::from_parts(line, column, offset)
+    }
+
+    /// Fills in the line and column of a position known only by its offset.
+    pub fn resolve(&self, pos: Pos) -> Pos {
+        if pos.is_resolved() {
+            pos
+        } else {
+            self.pos(pos.offset()).in_source(pos.source())
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_without_breaks() {
+        let index = LineIndex::new("ab\r\ncd\nef\r");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line(1), Some("ab"));
+        assert_eq!(index.line(2), Some("cd"));
+        assert_eq!(index.line(3), Some("ef\r"));
+        assert_eq!(index.line(0), None);
+        assert_eq!(index.line(4), None);
+    }
+
+    #[test]
+    fn positions_of_offsets() {
+        let index = LineIndex::new("ab\r\né\n");
+        assert_eq!(index.pos(1).to_string(), "1:2");
+        assert_eq!(index.pos(4).to_string(), "2:1");
+        assert_eq!(index.pos(5).to_string(), "2:1");
+        assert_eq!(index.pos(6).to_string(), "2:2");
+        assert_eq!(index.pos(100).to_string(), "3:1");
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::Deref;
+
+use crate::{
+    Input,
+    AsChar,
+    Location,
+    Exhausted,
+};
+
+/// An input that counts how far into the source it is, in bytes for `&str` and in
+/// tokens for slices. The offset is its [`Location`], so it is what
+/// [`spanned`](crate::Parser::spanned) reports and what a [`Report`](crate::Report)
+/// points at.
+///
+/// The slice returned by `diff` starts at the offset it was taken from, and its length in
+/// the same unit gives the end.
+/// # Example
+/// ```
+/// use rtor::{Input, Located, Location};
+///
+/// let mut input = Located::new("héllo");
+/// let start = input.clone();
+/// input.next();
+/// input.next();
+///
+/// assert_eq!(input.location(), 3);
+/// assert_eq!(*start.diff(&input), "hé");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Located<I> {
+    input: I,
+    offset: usize,
+}
+
+impl<I> Located<I> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            offset: 0,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+}
+
+impl<I> Deref for Located<I> {
+    type Target = I;
+
+    fn deref(&self) -> &Self::Target {
+        &self.input
+    }
+}
+
+impl<I> Input for Located<I> where I: Input {
+    type Token = I::Token;
+    type Tokens = I::Tokens;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        let mut rest = self.input.clone();
+        let t = rest.next()?;
+        self.offset += self.input.offset_to(&rest).unwrap_or(0);
+        self.input = rest;
+        Some(t)
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        self.input.peek()
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        Located {
+            input: self.input.diff(&other.input),
+            offset: self.offset
+        }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        self.input.tokens()
+    }
+
+    fn is_partial(&self) -> bool {
+        self.input.is_partial()
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        let offset = self.input.offset_to(&other.input)?;
+        (self.offset + offset == other.offset).then_some(offset)
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> where Self::Token: AsChar {
+        self.input.starts_with(literal)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let (head, rest) = self.input.split_at(offset);
+        let offset = self.offset + self.input.offset_to(&rest).unwrap_or(0);
+        (Located { input: head, offset: self.offset }, Located { input: rest, offset })
+    }
+
+    fn split_at_position<P>(&self, pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let (head, rest) = self.input.split_at_position(pred);
+        let offset = self.offset + self.input.offset_to(&rest).unwrap_or(0);
+        (Located { input: head, offset: self.offset }, Located { input: rest, offset })
+    }
+
+    fn position<P>(&self, pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        self.input.position(pred)
+    }
+
+    fn len_utf8(&self) -> usize where Self::Token: AsChar {
+        self.input.len_utf8()
+    }
+
+    fn charge(&self, cost: usize) -> Result<(), Exhausted> {
+        self.input.charge(cost)
+    }
+}
+
+impl<I> Location for Located<I> {
+    type Location = usize;
+
+    fn location(&self) -> Self::Location {
+        self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_inside_a_char_splits_before_it() {
+        let input = Located::new("héllo");
+        let (head, rest) = input.split_at(2);
+        assert_eq!((*head, *rest, rest.location()), ("h", "éllo", 1));
+        assert_eq!(input.offset_to(&rest), Some(1));
+
+        let (head, rest) = input.split_at(9);
+        assert_eq!((*head, *rest, rest.location()), ("héllo", "", 6));
+    }
+}
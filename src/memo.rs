@@ -0,0 +1,172 @@
+use std::{
+    fmt,
+    cell::RefCell,
+    collections::HashMap,
+};
+
+use crate::{
+    Input,
+    Pos,
+    Depth,
+    indent::Layout,
+};
+
+/// The results of one [`memo`](crate::combinator::memo) rule in one parse, so a rule
+/// that alternatives backtrack into again is only run once at each place.
+///
+/// Entries are keyed by the position the rule ran at and the reference and depth there,
+/// and hold the output and where the rule stopped, or the error it failed with. The
+/// table is typed by the rule's output and error, so it can hold inputs borrowed from
+/// the source. Make one for each rule and parse, or [`clear`](MemoTable::clear) it
+/// before parsing other source.
+pub struct MemoTable<O, E> {
+    entries: RefCell<HashMap<Key, Entry<O, E>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    pos: Pos,
+    reference: Pos,
+    depth: usize,
+}
+
+type Entry<O, E> = Result<(O, usize, Pos), E>;
+
+impl<O, E> MemoTable<O, E> {
+    pub fn new() -> Self {
+        Self { entries: RefCell::new(HashMap::new()) }
+    }
+
+    /// Forgets every result, for reusing the table on another parse.
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    /// The output, length and end of what the rule parsed where `input` is, or the
+    /// error it failed with.
+    pub(crate) fn get<I: Memoize>(&self, input: &I) -> Option<Entry<O, E>> where O: Clone, E: Clone {
+        self.entries.borrow().get(&Key::new(input)).cloned()
+    }
+
+    pub(crate) fn insert<I: Memoize>(&self, input: &I, entry: Entry<O, E>) {
+        self.entries.borrow_mut().insert(Key::new(input), entry);
+    }
+}
+
+impl Key {
+    fn new<I: Memoize>(input: &I) -> Self {
+        Self {
+            pos: input.location(),
+            reference: input.reference(),
+            depth: input.depth(),
+        }
+    }
+}
+
+impl<O, E> Default for MemoTable<O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O, E> fmt::Debug for MemoTable<O, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoTable")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// An input that [`memo`](crate::combinator::memo) can key results by and skip ahead on.
+pub trait Memoize: Input + Layout + Depth {
+    /// This input moved `offset` further on, to `end`, where a rule got to before.
+    fn memo_skip(&self, offset: usize, end: Pos) -> Self;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{Parser, ParseResult, ParseError, RichError, State};
+    use crate::char::{char, string};
+    use crate::combinator::{alt, cut, memo, limit_depth};
+
+    type Text<'a> = State<&'a str>;
+    type Error<'a> = Box<RichError<Text<'a>>>;
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn states_are_send() {
+        assert_send::<State<&str>>();
+        assert_send::<State<&str, crate::Scope<String, u32>>>();
+    }
+
+    #[test]
+    fn hit_gives_the_same_error_as_a_miss() {
+        let source = String::from("ax");
+        let runs = Cell::new(0);
+        let table = MemoTable::new();
+        let input = State::new(source.as_str());
+        let mut rule = memo(&table, |i| {
+            runs.set(runs.get() + 1);
+            (char('a'), cut(char('b'))).map(|_| ()).context("ab").parse(i)
+        });
+
+        let miss: Error = rule.parse(input.clone()).unwrap_err();
+        let hit: Error = rule.parse(input).unwrap_err();
+        assert_eq!(runs.get(), 1);
+        assert!(hit.is_committed());
+        assert_eq!(hit.to_string(), miss.to_string());
+        assert_eq!(hit.location(), miss.location());
+    }
+
+    #[test]
+    fn hit_skips_to_where_the_rule_stopped() {
+        let table = MemoTable::new();
+        let mut rule = memo(&table, string("ab"));
+        let input = State::new("abc");
+        let miss: ParseResult<Text, Text, Error> = rule.parse(input.clone());
+        let hit: ParseResult<Text, Text, Error> = rule.parse(input);
+        let (miss, hit) = (miss.unwrap(), hit.unwrap());
+        assert_eq!((*hit.0, *hit.1), (*miss.0, *miss.1));
+        assert_eq!(hit.1.pos(), miss.1.pos());
+    }
+
+    #[test]
+    fn depth_is_part_of_the_key() {
+        let runs = Cell::new(0);
+        let rule = |i: Text<'static>| -> ParseResult<&'static str, Text<'static>, Error<'static>> {
+            runs.set(runs.get() + 1);
+            limit_depth(1, string("a")).map(|s: Text<'static>| *s).parse(i)
+        };
+        let table = MemoTable::new();
+        let input = State::new("a");
+
+        assert!(memo(&table, rule).parse(input.clone()).is_ok());
+        assert!(limit_depth(1, memo(&table, rule)).parse(input).is_err());
+        assert_eq!((runs.get(), table.len()), (2, 2));
+    }
+
+    #[test]
+    fn alternatives_share_a_table() {
+        let runs = Cell::new(0);
+        let table = MemoTable::new();
+        let rule = |i: Text<'static>| -> ParseResult<char, Text<'static>, Error<'static>> {
+            runs.set(runs.get() + 1);
+            char('a').parse(i)
+        };
+        let mut parser = alt((memo(&table, rule).andl(char('b')), memo(&table, rule)));
+        assert!(parser.parse(State::new("a")).is_ok());
+        assert_eq!(runs.get(), 1);
+    }
+}
@@ -1,8 +1,16 @@
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    ops::Range,
+    rc::Rc,
+    cell::RefCell,
+};
 
 use crate::{
+    Input,
+    Location,
     ParseError,
     ParseResult,
+    recover::ErrorSink,
 };
 
 ///A trait for parser
@@ -11,6 +19,26 @@ pub trait Parser<I, E> {
 
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E>;
 
+    /// Parses `input` and returns the output, if any, together with every error that was
+    /// recovered from along the way. See [`recover`](crate::recover).
+    ///
+    /// If the parse fails the error that stopped it comes last, after those recovered
+    /// from before it.
+    fn parse_recovering(&mut self, input: I) -> (Option<Self::Output>, Vec<I::Item>)
+    where
+        I: ErrorSink<E>,
+        E: ParseError<I> + Into<I::Item>
+    {
+        match self.parse(input) {
+            Ok((o, mut i)) => (Some(o), i.take_errors()),
+            Err(mut e) => {
+                let mut errors = e.input_mut().map_or(vec![], |i| i.take_errors());
+                errors.push(e.into());
+                (None, errors)
+            }
+        }
+    }
+
     fn parse_iter(&mut self, input: I) -> ParseIter<'_, Self, I, E> where Self: Sized {
         ParseIter { 
             parser: self, 
@@ -114,16 +142,115 @@ pub trait Parser<I, E> {
         Ignore { parser: self }
     }
 
-    fn ref_mut(&mut self) -> RefMut<Self> where Self: Sized {
+    fn ref_mut(&mut self) -> RefMut<'_, Self> where Self: Sized {
         RefMut { parser: self }
     }
 
+    /// Borrows the parser as a trait object, for passing it where a `&mut dyn Parser` is
+    /// wanted or calling it through a `Box<P>`.
+    /// # Example
+    /// ```
+    /// use rtor::{Parser, ParseResult};
+    /// use rtor::char::char;
+    ///
+    /// let mut boxed = Box::new(char('a').andl(char('b')));
+    /// let result: ParseResult<char, &str> = (char('x'), boxed.as_dyn()).parse("xab").map(|((_, a), i)| (a, i));
+    /// assert_eq!(result, Ok(('a', "")));
+    /// ```
+    fn as_dyn(&mut self) -> &mut (dyn Parser<I, E, Output = Self::Output> + '_) where Self: Sized {
+        self
+    }
+
     fn expect(self, message: &str) -> Expect<Self> where Self: Sized {
         Expect { parser: self, message: message.to_owned() }
     }
 
+    /// On failure runs `strategy` from the same input, if it succeeds its output is used
+    /// and the error is kept in the input's [`ErrorSink`]. Incomplete and exhausted errors
+    /// are passed on.
+    ///
+    /// A strategy that consumes nothing, like [`insert`](crate::recover::insert), only
+    /// recovers once at a place. The input remembers it, see
+    /// [`ErrorSink::mark_stalled`], and if the parse comes back there without having
+    /// backtracked the error is passed on, so a repetition around the parser stops
+    /// instead of recovering for ever.
+    fn recover<R>(self, strategy: R) -> Recover<Self, R>
+    where
+        Self: Sized,
+        R: Parser<I, E, Output = Self::Output>
+    {
+        Recover { parser: self, strategy }
+    }
+
+    /// Labels the errors of this parser with the rule `label` instead of replacing them.
+    ///
+    /// The frames are kept by [`RichError`](crate::RichError). Error types without a
+    /// context stack, [`SimpleError`](crate::SimpleError) among them, drop the label and
+    /// only report the innermost failure.
+    fn context(self, label: &str) -> Context<Self> where Self: Sized {
+        Context { parser: self, label: label.to_owned() }
+    }
+
+    /// Pairs the output with the range of locations it was parsed from, from where the
+    /// parser started up to where it stopped.
+    /// # Example
+    /// ```
+    /// use rtor::{Parser, ParseResult, Located, State, Pos};
+    /// use rtor::char::{char, string};
+    ///
+    /// fn word(i: Located<&str>) -> ParseResult<(Located<&str>, std::ops::Range<usize>), Located<&str>> {
+    ///     char(' ').andr(string("hé").spanned()).parse(i)
+    /// }
+    ///
+    /// let ((output, span), _) = word(Located::new(" héllo")).unwrap();
+    /// assert_eq!((*output, span), ("hé", 1..4));
+    ///
+    /// fn pos(i: State<&str>) -> ParseResult<std::ops::Range<Pos>, State<&str>> {
+    ///     char(' ').andr(string("hé").spanned()).map(|(_, span)| span).parse(i)
+    /// }
+    ///
+    /// let (span, _) = pos(State::new(" héllo")).unwrap();
+    /// assert_eq!((span.start.column(), span.end.column()), (2, 4));
+    /// ```
+    fn spanned(self) -> Spanned<Self> where Self: Sized {
+        Spanned { parser: self }
+    }
+
+    /// Erases the type of the parser, so parsers of different types can be stored
+    /// together or returned from the arms of one `match`.
+    /// # Example
+    /// ```
+    /// use rtor::{Parser, BoxedParser, SimpleError};
+    /// use rtor::char::{char, ascii::{multi_alpha1, multi_digit1}};
+    ///
+    /// type Field<'a> = BoxedParser<'a, &'a str, SimpleError<&'a str>, String>;
+    ///
+    /// fn field<'a>(kind: &str) -> Field<'a> {
+    ///     match kind {
+    ///         "number" => multi_digit1.map(|s: &str| s.to_owned()).boxed(),
+    ///         "word" => multi_alpha1.map(|s: &str| s.to_uppercase()).boxed(),
+    ///         _ => char('-').map(|c: char| c.to_string()).boxed(),
+    ///     }
+    /// }
+    ///
+    /// let mut fields: Vec<Field> = ["word", "number", "dash", "word"].into_iter().map(field).collect();
+    /// let mut input = "abc12-de";
+    /// let mut output = vec![];
+    /// for field in &mut fields {
+    ///     let (o, rest) = field.parse(input).unwrap();
+    ///     output.push(o);
+    ///     input = rest;
+    /// }
+    /// assert_eq!(output, ["ABC", "12", "-", "DE"]);
+    /// ```
+    fn boxed<'a>(self) -> BoxedParser<'a, I, E, Self::Output> where Self: Sized + 'a {
+        Box::new(self)
+    }
 }
 
+/// A parser with its type erased, see [`Parser::boxed`].
+pub type BoxedParser<'a, I, E, O> = Box<dyn Parser<I, E, Output = O> + 'a>;
+
 impl<F, O, I, E> Parser<I, E> for F where F: FnMut(I) -> ParseResult<O, I, E> {
     type Output = O;
 
@@ -132,6 +259,50 @@ impl<F, O, I, E> Parser<I, E> for F where F: FnMut(I) -> ParseResult<O, I, E> {
     }
 }
 
+// There are no impls for every `Box<P>` and `&mut P`: a boxed or borrowed closure is a
+// closure too, so they would overlap with the one above. Trait objects aren't closures,
+// and `as_dyn`, `ref_mut` and `boxed` turn any parser into one of these.
+impl<O, I, E> Parser<I, E> for Box<dyn Parser<I, E, Output = O> + '_> {
+    type Output = O;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        (**self).parse(input)
+    }
+}
+
+impl<O, I, E> Parser<I, E> for &mut (dyn Parser<I, E, Output = O> + '_) {
+    type Output = O;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        (**self).parse(input)
+    }
+}
+
+/// A parser shared between several places in a grammar. It is borrowed while it runs, so
+/// reaching it again from inside itself fails with a committed error, use
+/// [`recursive`](crate::combinator::recursive) for parsers that refer to themselves.
+/// # Example
+/// ```
+/// use std::{rc::Rc, cell::RefCell};
+/// use rtor::{Parser, ParseResult};
+/// use rtor::char::char;
+///
+/// let a = Rc::new(RefCell::new(char('a')));
+/// let mut parser = (a.clone(), char('b'), a);
+/// let result: ParseResult<_, &str> = parser.parse("aba!");
+/// assert_eq!(result, Ok((('a', 'b', 'a'), "!")));
+/// ```
+impl<P, I, E> Parser<I, E> for Rc<RefCell<P>> where P: Parser<I, E>, E: ParseError<I> {
+    type Output = P::Output;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        match self.try_borrow_mut() {
+            Ok(mut parser) => parser.parse(input),
+            Err(_) => Err(E::expect("a shared parser that isn't already running".to_owned(), input).commit())
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ParseIter<'a, P, I, E> {
     parser: &'a mut P,
@@ -234,6 +405,7 @@ where
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
         match self.first.parse(input.clone()) {
             Ok(t) => Ok(t),
+            Err(e1) if e1.is_committed() => Err(e1),
             Err(e1) => match self.second.parse(input) {
                 Ok(t) => Ok(t),
                 Err(e2) => Err(e1.merge(e2))
@@ -366,11 +538,92 @@ where
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
         match self.parser.parse(input.clone()) {
             Ok(t) => Ok(t),
+            Err(e) if e.is_committed() => Err(e),
             Err(_) => Err(ParseError::expect(self.message.clone(), input))
         }
     }
 }
 
+#[derive(Clone)]
+pub struct Recover<P, R> {
+    parser: P,
+    strategy: R,
+}
+
+impl<P, R, I, E> Parser<I, E> for Recover<P, R> 
+where
+    I: Input + ErrorSink<E>,
+    E: ParseError<I>,
+    P: Parser<I, E>,
+    R: Parser<I, E, Output = P::Output>,
+{
+    type Output = P::Output;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        match self.parser.parse(input.clone()) {
+            Ok(t) => Ok(t),
+            Err(e) if e.needed().is_some() || e.exhaustion().is_some() => Err(e),
+            Err(e) => {
+                if input.is_stalled() {
+                    return Err(e);
+                }
+                match self.strategy.parse(input.clone()) {
+                    Ok((o, mut i)) => {
+                        i.push_error(e);
+                        if input.offset_to(&i) == Some(0) {
+                            i.mark_stalled();
+                        }
+                        Ok((o, i))
+                    }
+                    Err(_) => Err(e)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Context<P> {
+    parser: P,
+    label: String,
+}
+
+impl<P, I, E> Parser<I, E> for Context<P> 
+where
+    I: Clone,
+    E: ParseError<I>,
+    P: Parser<I, E>,
+{
+    type Output = P::Output;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        match self.parser.parse(input.clone()) {
+            Ok(t) => Ok(t),
+            Err(e) => Err(e.add_context(self.label.clone(), input))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Spanned<P> {
+    parser: P,
+}
+
+impl<P, I, E> Parser<I, E> for Spanned<P>
+where
+    I: Location,
+    P: Parser<I, E>,
+{
+    type Output = (P::Output, Range<I::Location>);
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        let start = input.location();
+        let (o, i) = self.parser.parse(input)?;
+        let end = i.location();
+        Ok(((o, start..end), i))
+    }
+}
+
 #[derive(Clone)]
 pub struct Chainl<A, B, V> {
     parser: A,
@@ -381,6 +634,7 @@ pub struct Chainl<A, B, V> {
 impl<I, E, A, B, F> Parser<I, E> for Chainl<A, B, A::Output> 
 where
     I: Clone,
+    E: ParseError<I>,
     A: Parser<I, E>,
     A::Output: Clone,
     B: Parser<I, E, Output = F>,
@@ -391,9 +645,15 @@ where
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
         let (mut left, mut input) = match self.parser.parse(input.clone()) {
             Ok(t) => t,
+            Err(e) if e.is_committed() => return Err(e),
             Err(_) => return Ok((self.value.clone(), input))
         };
-        while let Ok((f, i)) = self.op.parse(input.clone()) {
+        loop {
+            let (f, i) = match self.op.parse(input.clone()) {
+                Ok(t) => t,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => break
+            };
             let (right, i) = self.parser.parse(i)?;
             left = f(left, right);
             input = i;
@@ -411,6 +671,7 @@ pub struct Chainl1<A, B> {
 impl<A, B, I, E, F> Parser<I, E> for Chainl1<A, B> 
 where
     I: Clone,
+    E: ParseError<I>,
     A: Parser<I, E>,
     B: Parser<I, E, Output = F>,
     F: Fn(A::Output, A::Output) -> A::Output
@@ -419,7 +680,12 @@ where
 
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
         let (mut left, mut input) = self.parser.parse(input)?;
-        while let Ok((f, i)) = self.op.parse(input.clone()) {
+        loop {
+            let (f, i) = match self.op.parse(input.clone()) {
+                Ok(t) => t,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => break
+            };
             let (right, i) = self.parser.parse(i)?;
             left = f(left, right);
             input = i;
@@ -438,6 +704,7 @@ pub struct Chainr<A, B, V> {
 impl<I, E, A, B, F> Parser<I, E> for Chainr<A, B, A::Output> 
 where
     I: Clone,
+    E: ParseError<I>,
     A: Parser<I, E>,
     A::Output: Clone,
     B: Parser<I, E, Output = F>,
@@ -448,9 +715,15 @@ where
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
         let (mut left, mut input) = match self.parser.parse(input.clone()) {
             Ok(t) => t,
+            Err(e) if e.is_committed() => return Err(e),
             Err(_) => return Ok((self.value.clone(), input))
         };
-        while let Ok((f, i)) = self.op.parse(input.clone()) {
+        loop {
+            let (f, i) = match self.op.parse(input.clone()) {
+                Ok(t) => t,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => break
+            };
             let (right, i) = self.parse(i)?;
             left = f(left, right);
             input = i;
@@ -468,6 +741,7 @@ pub struct Chainr1<A, B> {
 impl<I, E, A, B, F> Parser<I, E> for Chainr1<A, B> 
 where
     I: Clone,
+    E: ParseError<I>,
     A: Parser<I, E>,
     B: Parser<I, E, Output = F>,
     F: Fn(A::Output, A::Output) -> A::Output
@@ -476,11 +750,34 @@ where
 
     fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
         let (mut left, mut input) = self.parser.parse(input)?;
-        while let Ok((f, i)) = self.op.parse(input.clone()) {
+        loop {
+            let (f, i) = match self.op.parse(input.clone()) {
+                Ok(t) => t,
+                Err(e) if e.is_committed() => return Err(e),
+                Err(_) => break
+            };
             let (right, i) = self.parse(i)?;
             left = f(left, right);
             input = i;
         }
         Ok((left, input))
     }
-}  
\ No newline at end of file
+}  
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimpleError;
+    use crate::char::char;
+
+    #[test]
+    fn shared_parser_reached_from_itself_fails() {
+        let shared: Rc<RefCell<BoxedParser<&str, SimpleError<&str>, char>>> = Rc::new(RefCell::new(char('x').boxed()));
+        let inner = shared.clone();
+        *shared.borrow_mut() = char('(').andr(inner).boxed();
+        let err = shared.clone().parse("((x").unwrap_err();
+        assert!(err.is_committed());
+        assert_eq!(err.message.as_deref(), Some("a shared parser that isn't already running"));
+        // break the cycle
+        *shared.borrow_mut() = char('x').boxed();
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    ops::Deref,
+    cmp::Ordering,
+};
+
+use crate::{
+    Input,
+    AsChar,
+    Location,
+    Exhausted,
+};
+
+/// An input holding only the data that has arrived so far, for protocols and files that
+/// are read in chunks.
+///
+/// Parsers that run out of a partial input fail with an
+/// [incomplete](crate::ParseError::incomplete) error telling how much more they need.
+/// The caller can append the data and parse again from the start, or call
+/// [`complete`](Partial::complete) once the last chunk is in.
+/// # Example
+/// ```
+/// use rtor::{Parser, Partial, SimpleError, Needed, ParseError};
+/// use rtor::char::string;
+///
+/// let mut parser = string::<_, SimpleError<_>>("hello");
+///
+/// let err = parser.parse(Partial::new("hel")).unwrap_err();
+/// assert_eq!(err.needed(), Some(Needed::Size(2)));
+///
+/// let (output, _) = parser.parse(Partial::new("hello")).unwrap();
+/// assert_eq!(*output, "hello");
+///
+/// let err = parser.parse(Partial::new("hel").complete()).unwrap_err();
+/// assert_eq!(err.needed(), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partial<I> {
+    input: I,
+    partial: bool,
+}
+
+impl<I> Partial<I> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            partial: true,
+        }
+    }
+
+    /// Marks that no more data will follow.
+    pub fn complete(self) -> Self {
+        Self { partial: false, ..self }
+    }
+
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+}
+
+impl<I> Deref for Partial<I> {
+    type Target = I;
+
+    fn deref(&self) -> &Self::Target {
+        &self.input
+    }
+}
+
+impl<I> Input for Partial<I> where I: Input {
+    type Token = I::Token;
+    type Tokens = I::Tokens;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        self.input.next()
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        self.input.peek()
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        // what was consumed is all there, however much may follow it
+        Partial {
+            input: self.input.diff(&other.input),
+            partial: false
+        }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        self.input.tokens()
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        self.input.offset_to(&other.input)
+    }
+
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> where Self::Token: AsChar {
+        self.input.starts_with(literal)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let (head, rest) = self.input.split_at(offset);
+        (Partial { input: head, partial: false }, Partial { input: rest, partial: self.partial })
+    }
+
+    fn split_at_position<P>(&self, pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let (head, rest) = self.input.split_at_position(pred);
+        (Partial { input: head, partial: false }, Partial { input: rest, partial: self.partial })
+    }
+
+    fn position<P>(&self, pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        self.input.position(pred)
+    }
+
+    fn len_utf8(&self) -> usize where Self::Token: AsChar {
+        self.input.len_utf8()
+    }
+
+    fn charge(&self, cost: usize) -> Result<(), Exhausted> {
+        self.input.charge(cost)
+    }
+}
+
+impl<I> Location for Partial<I> where I: Location {
+    type Location = I::Location;
+
+    fn location(&self) -> Self::Location {
+        self.input.location()
+    }
+
+    fn progress(&self, other: &Self) -> Option<Ordering> {
+        self.input.progress(&other.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn what_was_consumed_is_complete() {
+        let mut input = Partial::new("héllo");
+        let start = input;
+        input.next();
+        let head = start.diff(&input);
+        assert_eq!((*head, head.is_partial()), ("h", false));
+        assert!(input.is_partial());
+
+        let (head, rest) = start.split_at_position(|ch| *ch == 'l');
+        assert_eq!((*head, head.is_partial(), *rest, rest.is_partial()), ("hé", false, "llo", true));
+    }
+
+    #[test]
+    fn split_inside_a_char_splits_before_it() {
+        let input = Partial::new("héllo");
+        let (head, rest) = input.split_at(2);
+        assert_eq!((*head, *rest), ("h", "éllo"));
+        assert!(rest.is_partial());
+    }
+}
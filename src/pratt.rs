@@ -0,0 +1,397 @@
+//! Expressions with operators of different precedence, parsed from a table of operators
+//! rather than one layer of [`chainl1`](crate::Parser::chainl1) per precedence level.
+//!
+//! Each operator has a power, operators with a higher power bind tighter. Infix operators
+//! also have an [`Assoc`]iativity, and a prefix operator binds tighter than an infix or
+//! postfix operator of the same power after its operand.
+//! # Example
+//! ```
+//! use rtor::{Parser, ParseError, Recursive, SimpleError};
+//! use rtor::char::{char, ascii::multi_digit1};
+//! use rtor::combinator::{alt, between, recursive};
+//! use rtor::pratt::{Assoc, Pratt};
+//!
+//! fn expr<'a>() -> Recursive<'a, &'a str, SimpleError<&'a str>, String> {
+//!     recursive(|expr| {
+//!         let atom = alt((
+//!             multi_digit1.map(|s: &str| s.to_owned()),
+//!             between(char('('), expr, char(')')),
+//!         ));
+//!         Pratt::new(atom)
+//!             .infix(Assoc::Left, 1, alt((char('+'), char('-'))), |l, op, r| format!("({} {} {})", l, op, r))
+//!             .infix(Assoc::Left, 2, char('*'), |l, _, r| format!("({} * {})", l, r))
+//!             .infix(Assoc::Right, 3, char('^'), |l, _, r| format!("({} ^ {})", l, r))
+//!             .infix(Assoc::None, 0, char('='), |l, _, r| format!("({} = {})", l, r))
+//!             .prefix(2, char('-'), |_, e| format!("-{}", e))
+//!             .postfix(4, char('!'), |e, _| format!("{}!", e))
+//!     })
+//! }
+//!
+//! let mut expr = expr();
+//! assert_eq!(expr.parse("1+2*3-4").unwrap().0, "((1 + (2 * 3)) - 4)");
+//! assert_eq!(expr.parse("2^3^2!").unwrap().0, "(2 ^ (3 ^ 2!))");
+//! assert_eq!(expr.parse("-2*3+-(4)").unwrap().0, "((-2 * 3) + -4)");
+//! assert_eq!(expr.parse("1=2").unwrap().0, "(1 = 2)");
+//!
+//! let err = expr.parse("1=2=3").unwrap_err();
+//! assert_eq!(err.input, "=3");
+//! assert!(err.is_committed());
+//! ```
+
+use std::cell::RefCell;
+
+use crate::{
+    Parser,
+    ParseResult,
+    ParseError,
+    BoxedParser,
+    Depth,
+};
+
+/// How a chain of infix operators of the same power groups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`.
+    Right,
+    /// `a == b == c` is an error.
+    None,
+}
+
+/// How far trying an operator got. The argument passed in is handed back when the
+/// operator isn't applied.
+enum Tried<A, O, I> {
+    Unmatched(A),
+    Unapplied(A),
+    Folded(O, I),
+}
+
+/// What an operator goes on with once it has parsed: from the argument and the rest of
+/// the input, what to fold it with, or the argument back to leave it unapplied.
+type Then<'t, A, B, I, E> = dyn FnMut(A, I) -> Result<Result<(B, I), A>, E> + 't;
+
+/// An operator that folds what it parsed with `B` into the expression `O`. What it
+/// parsed stays on the stack while `then` parses the operands, which may use the same
+/// operator again.
+trait Op<I, E, A, B, O> {
+    /// Parses the operator and goes on with `then`. An error that isn't committed is
+    /// merged into `error` and the operator counts as unmatched.
+    fn apply(&self, input: I, arg: A, error: &mut Option<E>, then: &mut Then<'_, A, B, I, E>) -> Result<Tried<A, O, I>, E>;
+}
+
+struct Folding<P, F> {
+    parser: RefCell<P>,
+    fold: F,
+}
+
+impl<P, F, I, E, A, B, O> Op<I, E, A, B, O> for Folding<P, F>
+where
+    P: Parser<I, E>,
+    E: ParseError<I>,
+    F: Fn(P::Output, B) -> O
+{
+    fn apply(&self, input: I, arg: A, error: &mut Option<E>, then: &mut Then<'_, A, B, I, E>) -> Result<Tried<A, O, I>, E> {
+        let parsed = self.parser.borrow_mut().parse(input);
+        let (t, i) = match parsed {
+            Ok(t) => t,
+            Err(e) if e.is_committed() => return Err(e),
+            Err(e) => {
+                *error = Some(match error.take() {
+                    Some(prev) => prev.merge(e),
+                    None => e
+                });
+                return Ok(Tried::Unmatched(arg));
+            }
+        };
+        Ok(match then(arg, i)? {
+            Ok((b, i)) => Tried::Folded((self.fold)(t, b), i),
+            Err(arg) => Tried::Unapplied(arg),
+        })
+    }
+}
+
+type Prefix<'a, I, E, O> = Operator<Box<dyn Op<I, E, (), O, O> + 'a>>;
+
+type Postfix<'a, I, E, O> = Operator<Box<dyn Op<I, E, O, O, O> + 'a>>;
+
+type Infix<'a, I, E, O> = Operator<Box<dyn Op<I, E, O, (O, O), O> + 'a>>;
+
+struct Operator<T> {
+    assoc: Assoc,
+    power: u32,
+    op: T,
+}
+
+impl<T> Operator<T> {
+    /// The powers the operator binds with to its left and to its right.
+    fn binding(&self) -> (u32, u32) {
+        let power = self.power.saturating_mul(2);
+        match self.assoc {
+            Assoc::Right => (power, power),
+            Assoc::Left | Assoc::None => (power, power.saturating_add(1)),
+        }
+    }
+}
+
+struct DepthLimit<I> {
+    limit: usize,
+    depth: fn(&I) -> usize,
+    set_depth: fn(&mut I, usize),
+}
+
+impl<I> Clone for DepthLimit<I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I> Copy for DepthLimit<I> {}
+
+/// An expression parser built from an atom parser and tables of prefix, infix and
+/// postfix operators. Operators are tried in the order they were added, what they
+/// parse is passed to their fold along with the operands.
+///
+/// The parser recurses into the operands of prefix and infix operators, so long chains
+/// of prefix or right associative operators nest deeply. On inputs that track their
+/// [`Depth`], [`limit_depth`](Pratt::limit_depth) bounds that.
+pub struct Pratt<'a, I, E, O> {
+    atom: RefCell<BoxedParser<'a, I, E, O>>,
+    prefix: Vec<Prefix<'a, I, E, O>>,
+    infix: Vec<Infix<'a, I, E, O>>,
+    postfix: Vec<Postfix<'a, I, E, O>>,
+    depth: Option<DepthLimit<I>>,
+}
+
+impl<'a, I, E, O> Pratt<'a, I, E, O> where O: 'a {
+    pub fn new<P>(atom: P) -> Self where P: Parser<I, E, Output = O> + 'a {
+        Self {
+            atom: RefCell::new(atom.boxed()),
+            prefix: vec![],
+            infix: vec![],
+            postfix: vec![],
+            depth: None,
+        }
+    }
+
+    pub fn prefix<P, F>(mut self, power: u32, op: P, fold: F) -> Self
+    where
+        P: Parser<I, E> + 'a,
+        P::Output: 'a,
+        F: Fn(P::Output, O) -> O + 'a,
+        E: ParseError<I>
+    {
+        let op = Folding { parser: RefCell::new(op), fold };
+        self.prefix.push(Operator { assoc: Assoc::Right, power, op: Box::new(op) });
+        self
+    }
+
+    pub fn infix<P, F>(mut self, assoc: Assoc, power: u32, op: P, fold: F) -> Self
+    where
+        P: Parser<I, E> + 'a,
+        P::Output: 'a,
+        F: Fn(O, P::Output, O) -> O + 'a,
+        E: ParseError<I>
+    {
+        let op = Folding { parser: RefCell::new(op), fold: move |t, (l, r)| fold(l, t, r) };
+        self.infix.push(Operator { assoc, power, op: Box::new(op) });
+        self
+    }
+
+    pub fn postfix<P, F>(mut self, power: u32, op: P, fold: F) -> Self
+    where
+        P: Parser<I, E> + 'a,
+        P::Output: 'a,
+        F: Fn(O, P::Output) -> O + 'a,
+        E: ParseError<I>
+    {
+        let op = Folding { parser: RefCell::new(op), fold: move |t, o| fold(o, t) };
+        self.postfix.push(Operator { assoc: Assoc::Left, power, op: Box::new(op) });
+        self
+    }
+}
+
+impl<I, E, O> Pratt<'_, I, E, O> where I: Depth {
+    /// Counts each operand of a prefix or infix operator as one level of nesting of the
+    /// input, like [`limit_depth`](crate::combinator::limit_depth) does, and fails with
+    /// a committed error past `limit`.
+    /// # Example
+    /// ```
+    /// use rtor::{Parser, ParseResult, State};
+    /// use rtor::char::{char, ascii::multi_digit1};
+    /// use rtor::pratt::Pratt;
+    ///
+    /// type Input<'a> = State<&'a str>;
+    ///
+    /// let deep = "-".repeat(100_000) + "1";
+    /// let mut expr = Pratt::new(multi_digit1.map(|s: Input| s.len()))
+    ///     .prefix(1, char('-'), |_, n| n + 1)
+    ///     .limit_depth(64);
+    ///
+    /// let result: ParseResult<usize, Input> = expr.parse(State::new("---1"));
+    /// assert_eq!(result.unwrap().0, 4);
+    ///
+    /// let result: ParseResult<usize, Input> = expr.parse(State::new(&deep));
+    /// assert_eq!(result.unwrap_err().message.as_deref(), Some("nesting at most 64 deep"));
+    /// ```
+    pub fn limit_depth(self, limit: usize) -> Self {
+        let depth = DepthLimit { limit, depth: I::depth, set_depth: I::set_depth };
+        Self { depth: Some(depth), ..self }
+    }
+}
+
+impl<I, E, O> Pratt<'_, I, E, O>
+where
+    I: Clone,
+    E: ParseError<I>
+{
+    /// Parses an expression whose operators bind with at least `min` power.
+    fn expr(&self, input: I, min: u32) -> ParseResult<O, I, E> {
+        let mut error = None;
+        let prefixed = first(&self.prefix, &input, (), &mut error, |op, (), i| {
+            let (_, right) = op.binding();
+            self.operand(i, right.saturating_add(1)).map(Ok)
+        })?;
+        let (mut lhs, mut input) = match prefixed {
+            Tried::Folded(o, i) => (o, i),
+            Tried::Unmatched(()) | Tried::Unapplied(()) => match self.atom.borrow_mut().parse(input) {
+                Ok(t) => t,
+                Err(e) => return Err(match error {
+                    Some(prev) => prev.merge(e),
+                    None => e
+                })
+            }
+        };
+
+        let mut chained = None;
+        loop {
+            let postfixed = first(&self.postfix, &input, lhs, &mut None, |op, lhs, i| {
+                Ok(if op.binding().0 < min { Err(lhs) } else { Ok((lhs, i)) })
+            })?;
+            match postfixed {
+                Tried::Folded(o, i) => {
+                    (lhs, input) = (o, i);
+                    chained = None;
+                    continue;
+                }
+                Tried::Unapplied(o) => {
+                    lhs = o;
+                    break;
+                }
+                Tried::Unmatched(o) => lhs = o
+            }
+            let infixed = first(&self.infix, &input, lhs, &mut None, |op, lhs, i| {
+                let (left, right) = op.binding();
+                if left < min {
+                    return Ok(Err(lhs));
+                }
+                if op.assoc == Assoc::None && chained == Some(op.power) {
+                    let message = "parentheses around a chained non-associative operator".to_owned();
+                    return Err(E::expect(message, input.clone()).commit());
+                }
+                chained = (op.assoc == Assoc::None).then_some(op.power);
+                let (rhs, i) = self.operand(i, right)?;
+                Ok(Ok(((lhs, rhs), i)))
+            })?;
+            match infixed {
+                Tried::Folded(o, i) => (lhs, input) = (o, i),
+                Tried::Unmatched(o) | Tried::Unapplied(o) => {
+                    lhs = o;
+                    break;
+                }
+            }
+        }
+        Ok((lhs, input))
+    }
+
+    /// Parses the operand of a prefix or infix operator, one level deeper.
+    fn operand(&self, mut input: I, min: u32) -> ParseResult<O, I, E> {
+        let Some(limit) = self.depth else {
+            return self.expr(input, min);
+        };
+        let depth = (limit.depth)(&input);
+        if depth >= limit.limit {
+            return Err(E::expect(format!("nesting at most {} deep", limit.limit), input).commit());
+        }
+        (limit.set_depth)(&mut input, depth + 1);
+        let (o, mut i) = self.expr(input, min)?;
+        (limit.set_depth)(&mut i, depth);
+        Ok((o, i))
+    }
+}
+
+impl<I, E, O> Parser<I, E> for Pratt<'_, I, E, O>
+where
+    I: Clone,
+    E: ParseError<I>
+{
+    type Output = O;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        self.expr(input, 0)
+    }
+}
+
+/// Tries the operators in turn until one parses, passing the argument on to the next
+/// one while they don't.
+fn first<A, B, I, E, O, T>(
+    ops: &[Operator<Box<T>>],
+    input: &I,
+    mut arg: A,
+    error: &mut Option<E>,
+    mut then: impl FnMut(&Operator<Box<T>>, A, I) -> Result<Result<(B, I), A>, E>
+) -> Result<Tried<A, O, I>, E>
+where
+    I: Clone,
+    T: Op<I, E, A, B, O> + ?Sized
+{
+    for op in ops {
+        match op.op.apply(input.clone(), arg, error, &mut |a, i| then(op, a, i))? {
+            Tried::Unmatched(a) => arg = a,
+            tried => return Ok(tried)
+        }
+    }
+    Ok(Tried::Unmatched(arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SimpleError, State};
+    use crate::char::{char, ascii::multi_digit1};
+    use crate::combinator::alt;
+
+    type Text<'a> = State<&'a str>;
+    type Error<'a> = Box<SimpleError<Text<'a>>>;
+
+    fn calc<'a>() -> Pratt<'a, Text<'a>, Error<'a>, String> {
+        Pratt::new(multi_digit1.map(|s: Text| s.to_string()))
+            .infix(Assoc::Left, 1, alt((char('+'), char('-'))), |l, op, r| format!("({} {} {})", l, op, r))
+            .infix(Assoc::Right, 2, char('^'), |l, _, r| format!("({} ^ {})", l, r))
+            .prefix(3, alt((char('-'), char('!'))), |op, e| format!("{}{}", op, e))
+            .limit_depth(8)
+    }
+
+    #[test]
+    fn reused_after_failing_mid_expression() {
+        let mut expr = calc();
+        assert!(expr.parse(State::new("1+-")).is_err());
+        assert!(expr.parse(State::new("!1^")).is_err());
+        assert_eq!(expr.parse(State::new("1-!2+3")).unwrap().0, "((1 - !2) + 3)");
+    }
+
+    #[test]
+    fn right_associative_chains_are_limited() {
+        let mut expr = calc();
+        assert_eq!(expr.parse(State::new("1^2^3")).unwrap().0, "(1 ^ (2 ^ 3))");
+        let err = expr.parse(State::new("1^1^1^1^1^1^1^1^1^1")).unwrap_err();
+        assert!(err.is_committed());
+        assert_eq!(err.input.pos().column(), 19);
+    }
+
+    #[test]
+    fn left_associative_chains_are_not_limited() {
+        let chain = vec!["1"; 100].join("+");
+        assert!(calc().parse(State::new(&chain)).is_ok());
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    fmt,
+    io::{self, Read},
+    collections::VecDeque,
+};
+
+use crate::{
+    Input,
+    Location,
+    replay::{Replay, Source, Tokens},
+};
+
+const CHUNK: usize = 8 * 1024;
+
+/// An input over the bytes of any [`Read`], for sources too big to load at once.
+///
+/// Data is read in chunks into a buffer shared by all clones of the input. Each clone is
+/// a cursor into it, so backtracking is as cheap as with a slice. The buffer only keeps
+/// data from the oldest cursor still alive onwards, everything before it is dropped.
+///
+/// A read error ends the input like the end of the data would, the error itself can be
+/// taken with [`take_error`](ReadInput::take_error).
+/// # Example
+/// ```
+/// use std::io::Cursor;
+/// use rtor::{Parser, ParseResult, ReadInput};
+/// use rtor::char::{char, ascii::multi_digit1};
+/// use rtor::combinator::sep_by;
+///
+/// type Input = ReadInput<Cursor<&'static [u8]>>;
+///
+/// fn numbers(i: Input) -> ParseResult<Vec<Vec<u8>>, Input> {
+///     sep_by(multi_digit1.map(|d: Input| d.to_vec()), char(',')).parse(i)
+/// }
+///
+/// let (output, _) = numbers(ReadInput::new(Cursor::new(b"12,345,6"))).unwrap();
+/// assert_eq!(output, [b"12".to_vec(), b"345".to_vec(), b"6".to_vec()]);
+/// ```
+pub struct ReadInput<R: Read> {
+    replay: Replay<Reader<R>>,
+}
+
+/// Reads the data in chunks and keeps the error that ended it.
+pub struct Reader<R> {
+    reader: R,
+    error: Option<io::Error>,
+}
+
+impl<R> Source for Reader<R> where R: Read {
+    type Item = u8;
+
+    fn fill(&mut self, items: &mut VecDeque<u8>) -> bool {
+        let mut chunk = [0; CHUNK];
+        loop {
+            match self.reader.read(&mut chunk) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    items.extend(&chunk[..n]);
+                    return true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.error = Some(e);
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+impl<R> ReadInput<R> where R: Read {
+    pub fn new(reader: R) -> Self {
+        Self {
+            replay: Replay::new(Reader { reader, error: None }),
+        }
+    }
+
+    /// How many bytes into the data the cursor is.
+    pub fn offset(&self) -> usize {
+        self.replay.pos()
+    }
+
+    /// Takes the error that cut the data short, if there was one.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.replay.with_source(|reader| reader.error.take())
+    }
+
+    /// How many bytes the buffer is holding on to right now.
+    pub fn buffered(&self) -> usize {
+        self.replay.buffered()
+    }
+
+    /// The remaining bytes, reading the rest of the data for an unbounded input.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.tokens().collect()
+    }
+}
+
+impl<R> Clone for ReadInput<R> where R: Read {
+    fn clone(&self) -> Self {
+        Self { replay: self.replay.clone() }
+    }
+}
+
+impl<R> fmt::Debug for ReadInput<R> where R: Read {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReadInput").field(&self.replay).finish()
+    }
+}
+
+impl<R> Input for ReadInput<R> where R: Read {
+    type Token = u8;
+    type Tokens = Tokens<Reader<R>>;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        self.replay.next()
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        self.replay.peek()
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        Self { replay: self.replay.diff(&other.replay) }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        Tokens::new(self.replay.clone())
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        self.replay.offset_to(&other.replay)
+    }
+}
+
+impl<R> Location for ReadInput<R> where R: Read {
+    type Location = usize;
+
+    fn location(&self) -> Self::Location {
+        self.replay.pos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::Parser;
+    use crate::char::string;
+
+    /// Hands out its data a few bytes per read and counts the reads, failing at the end
+    /// if `fail` is set.
+    struct Trickle {
+        data: &'static [u8],
+        step: usize,
+        fail: bool,
+        reads: Rc<Cell<usize>>,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads.set(self.reads.get() + 1);
+            if self.data.is_empty() && self.fail {
+                return Err(io::Error::other("broken pipe"));
+            }
+            let n = self.step.min(self.data.len()).min(buf.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn trickle(data: &'static [u8], step: usize) -> (ReadInput<Trickle>, Rc<Cell<usize>>) {
+        let reads = Rc::new(Cell::new(0));
+        (ReadInput::new(Trickle { data, step, fail: false, reads: reads.clone() }), reads)
+    }
+
+    #[test]
+    fn refills_across_reads() {
+        let (input, reads) = trickle(b"hello world!", 3);
+        let (output, rest): (_, ReadInput<_>) = string::<_, crate::SimpleError<_>>("hello world").parse(input).unwrap();
+
+        assert_eq!(output.to_vec(), b"hello world");
+        assert_eq!(reads.get(), 4);
+        assert_eq!(rest.to_vec(), b"!");
+    }
+
+    #[test]
+    fn checkpoints_keep_data_until_dropped() {
+        let (mut input, _) = trickle(b"abcdefghijkl", 3);
+        let checkpoint = input.clone();
+        for _ in 0..7 {
+            input.next();
+        }
+        assert_eq!(input.buffered(), 9);
+
+        drop(checkpoint);
+        assert_eq!(input.buffered(), 9);
+
+        let copy = input.clone();
+        assert_eq!(input.buffered(), 2);
+        assert_eq!(copy.to_vec(), b"hijkl");
+    }
+
+    #[test]
+    fn backtracking_replays_after_trimming() {
+        let (mut input, reads) = trickle(b"abcdefghijkl", 3);
+        input.next();
+        input.next();
+        let checkpoint = input.clone();
+        for _ in 0..8 {
+            input.next();
+        }
+        let _ = input.clone();
+
+        assert_eq!(input.buffered(), 10);
+        assert_eq!(checkpoint.to_vec(), b"cdefghijkl");
+        assert_eq!(reads.get(), 5);
+    }
+
+    #[test]
+    fn read_error_ends_input() {
+        let reads = Rc::new(Cell::new(0));
+        let input = ReadInput::new(Trickle { data: b"abcd", step: 3, fail: true, reads });
+        assert_eq!(input.to_vec(), b"abcd");
+        assert_eq!(input.take_error().map(|e| e.to_string()), Some("broken pipe".to_owned()));
+        assert!(input.take_error().is_none());
+    }
+}
@@ -0,0 +1,415 @@
+//! Recovering from errors so a parse can carry on and report everything it found.
+//!
+//! [`Parser::recover`] runs a strategy from this module when a parser fails. If the
+//! strategy succeeds its output stands in for the missing one and the error is pushed
+//! into the input's [`ErrorSink`], which for a [`State`] is its data. Since the errors
+//! travel with the input they are dropped again if an enclosing alternative backtracks.
+//! # Example
+//! ```
+//! use rtor::{Parser, ParseResult, Pos, RichError, State};
+//! use rtor::char::char;
+//! use rtor::combinator::sep_by;
+//! use rtor::recover::{skip_until, Recovered};
+//!
+//! type Input<'a> = State<&'a str, Vec<Recovered<Pos>>>;
+//!
+//! fn items(i: Input) -> ParseResult<Vec<char>, Input, RichError<Input>> {
+//!     sep_by(char('a').recover(skip_until(char(';'), || '?')), char(';'))(i)
+//! }
+//!
+//! let mut parser = items;
+//! let (items, errors) = parser.parse_recovering(State::with_data(vec![], "a;xx;a"));
+//!
+//! assert_eq!(items, Some(vec!['a', '?', 'a']));
+//! assert_eq!(errors.len(), 1);
+//! assert_eq!(errors[0].to_string(), "expected 'a', found 'x' at 1:3");
+//! ```
+
+use std::{
+    fmt,
+    mem,
+    ops::Range,
+};
+
+use crate::{
+    Parser,
+    Input,
+    Location,
+    ParseResult,
+    ParseError,
+    Needed,
+    RichError,
+    SimpleError,
+    State,
+    Diagnostic,
+    SourceSpan,
+};
+
+/// Somewhere to keep the errors a parse recovered from.
+pub trait ErrorSink<E> {
+    type Item;
+
+    fn push_error(&mut self, error: E);
+
+    /// How many errors have been pushed and not taken yet.
+    fn error_count(&self) -> usize;
+
+    fn take_errors(&mut self) -> Vec<Self::Item>;
+
+    /// Remembers that a recovery at the current place consumed nothing, see
+    /// [`Parser::recover`]. Sinks that can't tell places apart ignore it.
+    fn mark_stalled(&mut self) {}
+
+    /// Whether a recovery at the current place consumed nothing and no error was
+    /// recovered from since.
+    fn is_stalled(&self) -> bool {
+        false
+    }
+}
+
+impl<E, T> ErrorSink<E> for Vec<T> where E: Into<T> {
+    type Item = T;
+
+    fn push_error(&mut self, error: E) {
+        self.push(error.into());
+    }
+
+    fn error_count(&self) -> usize {
+        self.len()
+    }
+
+    fn take_errors(&mut self) -> Vec<Self::Item> {
+        mem::take(self)
+    }
+}
+
+impl<I, Data, T, E> ErrorSink<E> for State<I, Data, T> where Data: ErrorSink<E> + Clone {
+    type Item = Data::Item;
+
+    fn push_error(&mut self, error: E) {
+        self.data.push_error(error)
+    }
+
+    fn error_count(&self) -> usize {
+        self.data.error_count()
+    }
+
+    fn take_errors(&mut self) -> Vec<Self::Item> {
+        self.data.take_errors()
+    }
+
+    fn mark_stalled(&mut self) {
+        self.stalled = Some((self.pos(), self.error_count()));
+    }
+
+    fn is_stalled(&self) -> bool {
+        self.stalled == Some((self.pos(), self.error_count()))
+    }
+}
+
+/// An error that was recovered from, detached from the input it happened at so it can be
+/// kept in that input's data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovered<L> {
+    pub location: L,
+    pub message: String,
+    pub notes: Vec<String>,
+}
+
+impl<I> From<RichError<I>> for Recovered<I::Location>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: fmt::Display + SourceSpan
+{
+    fn from(error: RichError<I>) -> Self {
+        Recovered {
+            location: error.location(),
+            message: error.message(),
+            notes: error.notes(),
+        }
+    }
+}
+
+impl<I> From<SimpleError<I>> for Recovered<I::Location>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: SourceSpan
+{
+    fn from(error: SimpleError<I>) -> Self {
+        Recovered {
+            location: error.input.location(),
+            message: error.message(),
+            notes: vec![],
+        }
+    }
+}
+
+impl<L, E> From<Box<E>> for Recovered<L> where E: Into<Recovered<L>> {
+    fn from(error: Box<E>) -> Self {
+        (*error).into()
+    }
+}
+
+impl<L> fmt::Display for Recovered<L> where L: fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.location)
+    }
+}
+
+impl<L> Diagnostic for Recovered<L> where L: SourceSpan {
+    fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn span(&self, source: &str) -> Range<usize> {
+        self.location.source_span(source)
+    }
+
+    fn notes(&self) -> Vec<String> {
+        self.notes.clone()
+    }
+
+    fn line_column(&self) -> Option<(usize, usize)> {
+        self.location.line_column()
+    }
+}
+
+/// Whether `error` has to be passed on rather than skipped over.
+fn fatal<I, E: ParseError<I>>(error: &E) -> bool {
+    error.is_committed() || error.needed().is_some() || error.exhaustion().is_some()
+}
+
+/// Steps over one token while skipping, failing if the input runs out.
+fn skip_token<I, E>(mut input: I) -> Result<I, E>
+where
+    I: Input,
+    E: ParseError<I>
+{
+    match input.next() {
+        Some(_) => Ok(input),
+        None if input.is_partial() => Err(E::incomplete(Needed::Unknown, input)),
+        None => Err(E::unexpect(input)),
+    }
+}
+
+/// Skips at least one token and then up to where `sync` would match, without consuming
+/// it, and returns `fallback()`. Fails if the input runs out first, and passes on
+/// committed, incomplete and exhausted errors from `sync`.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, SimpleError, ParseError};
+/// use rtor::char::char;
+/// use rtor::recover::skip_until;
+///
+/// fn parser(i: &str) -> ParseResult<(), &str> {
+///     skip_until(char(';'), || ())(i)
+/// }
+///
+/// assert_eq!(parser("ab;c"), Ok(((), ";c")));
+/// assert_eq!(parser(";;c"), Ok(((), ";c")));
+/// assert_eq!(parser("ab"), Err(SimpleError::unexpect("")));
+/// ```
+pub fn skip_until<S, F, O, I, E>(mut sync: S, mut fallback: F) -> impl FnMut(I) -> ParseResult<O, I, E>
+where
+    I: Input,
+    E: ParseError<I>,
+    S: Parser<I, E>,
+    F: FnMut() -> O
+{
+    move |input: I| {
+        let mut input = skip_token(input)?;
+        loop {
+            match sync.parse(input.clone()) {
+                Ok(_) => return Ok((fallback(), input)),
+                Err(e) if fatal(&e) => return Err(e),
+                Err(_) => input = skip_token(input)?,
+            }
+        }
+    }
+}
+
+/// Skips a group that starts with `open` up to its matching `close`, nested groups
+/// included, and returns `fallback()`. Committed, incomplete and exhausted errors from
+/// `open` and `close` are passed on.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, SimpleError};
+/// use rtor::char::char;
+/// use rtor::recover::skip_balanced;
+///
+/// fn parser(i: &str) -> ParseResult<(), &str> {
+///     skip_balanced(char('('), char(')'), || ())(i)
+/// }
+///
+/// assert_eq!(parser("(a(b)c)d"), Ok(((), "d")));
+/// assert_eq!(parser("(a(b)c"), Err(SimpleError { input: "", message: None, committed: false, needed: None, exhausted: None }));
+/// ```
+pub fn skip_balanced<L, R, F, O, I, E>(mut open: L, mut close: R, mut fallback: F) -> impl FnMut(I) -> ParseResult<O, I, E>
+where
+    I: Input,
+    E: ParseError<I>,
+    L: Parser<I, E>,
+    R: Parser<I, E>,
+    F: FnMut() -> O
+{
+    move |input: I| {
+        let (_, mut input) = open.parse(input)?;
+        let mut depth = 1;
+        loop {
+            match close.parse(input.clone()) {
+                Ok((_, i)) => {
+                    input = i;
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok((fallback(), input));
+                    }
+                    continue;
+                }
+                Err(e) if fatal(&e) => return Err(e),
+                Err(_) => {}
+            }
+            match open.parse(input.clone()) {
+                Ok((_, i)) => {
+                    input = i;
+                    depth += 1;
+                }
+                Err(e) if fatal(&e) => return Err(e),
+                Err(_) => input = skip_token(input)?,
+            }
+        }
+    }
+}
+
+/// Pretends the missing token `value` was there, without consuming input. See
+/// [`Parser::recover`] for how this is kept from recovering at the same place for ever.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, Pos, SimpleError, State};
+/// use rtor::char::char;
+/// use rtor::recover::{insert, Recovered};
+///
+/// type Input<'a> = State<&'a str, Vec<Recovered<Pos>>>;
+///
+/// fn parser(i: Input) -> ParseResult<(char, char), Input, SimpleError<Input>> {
+///     (char('a'), char(';').recover(insert(';'))).parse(i)
+/// }
+///
+/// let mut parser = parser;
+/// let (output, errors) = parser.parse_recovering(State::with_data(vec![], "a"));
+///
+/// assert_eq!(output, Some(('a', ';')));
+/// assert_eq!(errors[0].to_string(), "unexpected end of input at 1:2");
+/// ```
+pub fn insert<O, I, E>(value: O) -> impl FnMut(I) -> ParseResult<O, I, E>
+where
+    O: Clone
+{
+    move |input: I| Ok((value.clone(), input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Budget, Exhausted, Partial, Pos};
+    use crate::char::char;
+    use crate::combinator::{cut, many, metered, preceded, sep_by};
+
+    type Input<'a> = State<&'a str, Vec<Recovered<Pos>>>;
+
+    type Error<'a> = Box<RichError<Input<'a>>>;
+
+    #[test]
+    fn fatal_error_comes_after_recovered_ones() {
+        let mut parser = |i: Input<'static>| -> ParseResult<_, _, Error<'static>> {
+            (sep_by(char('a').recover(skip_until(char(';'), || '?')), char(';')), char('.')).parse(i)
+        };
+        let (output, errors) = parser.parse_recovering(State::with_data(vec![], "a;xx;a!"));
+        let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+
+        assert_eq!(output, None);
+        assert_eq!(errors, ["expected 'a', found 'x' at 1:3", "expected '.', found '!' at 1:7"]);
+    }
+
+    #[test]
+    fn backtracking_drops_recovered_errors() {
+        let mut parser = |i: Input<'static>| -> ParseResult<_, _, Error<'static>> {
+            (char('a').recover(insert('a')), char('b')).or((char('c'), char('b'))).parse(i)
+        };
+        let (output, errors) = parser.parse_recovering(State::with_data(vec![], "cb"));
+
+        assert_eq!(output, Some(('c', 'b')));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn recovery_without_progress_stops_repetition() {
+        let mut parser = |i: Input<'static>| -> ParseResult<_, _, Error<'static>> {
+            many(char('a').recover(insert('a'))).parse(i)
+        };
+        let (output, errors) = parser.parse_recovering(State::with_data(vec![], "aab"));
+
+        assert_eq!(output, Some(vec!['a', 'a', 'a']));
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn recovering_parser_keeps_nothing_between_parses() {
+        let mut parser = many(char('a').recover(insert('a')));
+        for _ in 0..2 {
+            let (output, errors) = Parser::<_, Error>::parse_recovering(&mut parser, State::with_data(vec![], "b"));
+            assert_eq!(output, Some(vec!['a']));
+            assert_eq!(errors.len(), 1);
+        }
+    }
+
+    #[test]
+    fn skip_until_skips_at_least_one_token() {
+        let mut parser = |i: Input<'static>| -> ParseResult<_, _, Error<'static>> {
+            many(char('a').recover(skip_until(char('a'), || '?'))).parse(i)
+        };
+        let (output, errors) = parser.parse_recovering(State::with_data(vec![], "abba"));
+
+        assert_eq!(output, Some(vec!['a', '?', 'a']));
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn skip_until_passes_on_committed_errors() {
+        let mut parser = skip_until(preceded(char('!'), cut(char(';'))), || ());
+        let err: SimpleError<&str> = parser.parse("x!y;").unwrap_err();
+
+        assert!(err.is_committed());
+        assert_eq!(err.input, "y;");
+    }
+
+    #[test]
+    fn skip_until_passes_on_exhausted_errors() {
+        let budget = Budget::new().fuel(2);
+        let mut parser = skip_until(metered(&budget, char(';')), || ());
+        let err: SimpleError<&str> = parser.parse("xyz;").unwrap_err();
+
+        assert_eq!(err.exhaustion(), Some(Exhausted::Fuel));
+    }
+
+    #[test]
+    fn skipping_out_of_partial_input_is_incomplete() {
+        let mut parser = skip_until(char(';'), || ());
+        let err: SimpleError<Partial<&str>> = parser.parse(Partial::new("ab")).unwrap_err();
+        assert!(err.needed().is_some());
+
+        let mut parser = skip_balanced(char('('), char(')'), || ());
+        let err: SimpleError<Partial<&str>> = parser.parse(Partial::new("(a(b)")).unwrap_err();
+        assert!(err.needed().is_some());
+    }
+
+    #[test]
+    fn skip_balanced_passes_on_committed_errors() {
+        let mut parser = skip_balanced(char('('), preceded(char(')'), cut(char(')'))), || ());
+        let err: SimpleError<&str> = parser.parse("(a)x").unwrap_err();
+
+        assert!(err.is_committed());
+        assert_eq!(err.input, "x");
+    }
+}
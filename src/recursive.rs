@@ -0,0 +1,247 @@
+use std::{
+    rc::{Rc, Weak},
+    cell::RefCell,
+};
+
+use crate::{
+    Parser,
+    ParseResult,
+    ParseError,
+    BoxedParser,
+};
+
+type Define<'a, I, E, O> = Rc<dyn Fn() -> BoxedParser<'a, I, E, O> + 'a>;
+
+struct Rule<'a, I, E, O> {
+    define: Option<Define<'a, I, E, O>>,
+    idle: Vec<BoxedParser<'a, I, E, O>>,
+    running: usize,
+}
+
+// anything, so that rules with different outputs can share a grammar
+trait Member {}
+
+impl<T: ?Sized> Member for T {}
+
+/// The rules declared together, which live as long as one of them has an owner.
+type Grammar<'a> = Rc<RefCell<Vec<Rc<dyn Member + 'a>>>>;
+
+enum Handle<'a, I, E, O> {
+    Owned(Rc<RefCell<Rule<'a, I, E, O>>>, Grammar<'a>),
+    Unowned(Weak<RefCell<Rule<'a, I, E, O>>>),
+}
+
+/// A parser that can refer to itself, built by [`recursive`](crate::combinator::recursive)
+/// or by [`declare`](Recursive::declare) and [`define`](Declared::define).
+///
+/// Parsers need `&mut self` to run, so while one is running a nested use of the rule
+/// can't run it too. The rule instead builds a parser from its definition for each
+/// level of nesting it reaches, and keeps only the outermost one once it's done.
+///
+/// Rules declared from one another with [`Declared::declare`] make up a grammar, and
+/// the handle [`define`](Declared::define) returns for any of them keeps all of them
+/// alive, so a function can build several rules and return just the one to start
+/// from. The handles a definition refers to, from [`Declared::handle`], don't keep
+/// anything alive, so the grammar is freed with the last handle `define` returned.
+/// Running a rule whose grammar is gone, or that was never defined, fails with a
+/// committed error.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, Recursive};
+/// use rtor::char::{char, ascii::multi_digit1};
+/// use rtor::combinator::{alt, between};
+///
+/// // expr = atom ('+' atom)*, atom = number | '(' expr ')'
+/// fn expr<'a>() -> Recursive<'a, &'a str, rtor::SimpleError<&'a str>, i64> {
+///     let expr = Recursive::declare();
+///     let atom = expr.declare();
+///     let expr_handle = expr.handle();
+///     let atom_handle = atom.handle();
+///     atom.define(move || alt((
+///         multi_digit1.map(|s: &str| s.parse().unwrap()),
+///         between(char('('), expr_handle.clone(), char(')')),
+///     )));
+///     expr.define(move || atom_handle.clone().chainl1(char('+').map(|_| |a, b| a + b)))
+/// }
+///
+/// let result: ParseResult<i64, &str> = expr().parse("1+(2+(3+4))+5");
+/// assert_eq!(result, Ok((15, "")));
+/// ```
+pub struct Recursive<'a, I, E, O> {
+    handle: Handle<'a, I, E, O>,
+}
+
+/// A [`Recursive`] rule that has been declared and not defined yet. It is defined once,
+/// by [`define`](Declared::define), which takes the declaration.
+pub struct Declared<'a, I, E, O> {
+    rule: Rc<RefCell<Rule<'a, I, E, O>>>,
+    grammar: Grammar<'a>,
+}
+
+impl<'a, I, E, O> Recursive<'a, I, E, O> {
+    /// A rule that can be used in other parsers now, through its
+    /// [handles](Declared::handle), and defined later, for rules that refer to each
+    /// other.
+    pub fn declare() -> Declared<'a, I, E, O> where I: 'a, E: 'a, O: 'a {
+        Declared::new(Rc::default())
+    }
+
+    fn rule(&self) -> Option<Rc<RefCell<Rule<'a, I, E, O>>>> {
+        match &self.handle {
+            Handle::Owned(rule, _) => Some(rule.clone()),
+            Handle::Unowned(rule) => rule.upgrade(),
+        }
+    }
+}
+
+impl<'a, I, E, O> Declared<'a, I, E, O> {
+    fn new(grammar: Grammar<'a>) -> Self where I: 'a, E: 'a, O: 'a {
+        let rule = Rc::new(RefCell::new(Rule { define: None, idle: vec![], running: 0 }));
+        grammar.borrow_mut().push(rule.clone());
+        Self { rule, grammar }
+    }
+
+    /// Another rule in the same grammar as this one, which lives as long as it does.
+    pub fn declare<O2>(&self) -> Declared<'a, I, E, O2> where I: 'a, E: 'a, O2: 'a {
+        Declared::new(self.grammar.clone())
+    }
+
+    /// A handle on the rule that doesn't keep it alive, for the definitions of this and
+    /// other rules to refer to it by.
+    pub fn handle(&self) -> Recursive<'a, I, E, O> {
+        Recursive { handle: Handle::Unowned(Rc::downgrade(&self.rule)) }
+    }
+
+    /// Defines the rule with `define`, which builds its parser and may be called again for
+    /// each level of nesting, and returns a handle that owns it along with the rest of
+    /// its grammar.
+    pub fn define<F, P>(self, define: F) -> Recursive<'a, I, E, O>
+    where
+        F: Fn() -> P + 'a,
+        P: Parser<I, E, Output = O> + 'a
+    {
+        self.rule.borrow_mut().define = Some(Rc::new(move || define().boxed()));
+        Recursive { handle: Handle::Owned(self.rule, self.grammar) }
+    }
+}
+
+impl<I, E, O> Clone for Recursive<'_, I, E, O> {
+    fn clone(&self) -> Self {
+        match &self.handle {
+            Handle::Owned(rule, grammar) => Self { handle: Handle::Owned(rule.clone(), grammar.clone()) },
+            Handle::Unowned(rule) => Self { handle: Handle::Unowned(rule.clone()) },
+        }
+    }
+}
+
+impl<I, E, O> Parser<I, E> for Recursive<'_, I, E, O> where E: ParseError<I> {
+    type Output = O;
+
+    fn parse(&mut self, input: I) -> ParseResult<Self::Output, I, E> {
+        let Some(rule) = self.rule() else {
+            return Err(E::expect("a recursive rule that is still alive".to_owned(), input).commit());
+        };
+        let idle = rule.borrow_mut().idle.pop();
+        let mut parser = match idle {
+            Some(parser) => parser,
+            None => match rule.borrow().define.clone() {
+                Some(define) => define(),
+                None => return Err(E::expect("a recursive rule that is defined".to_owned(), input).commit())
+            }
+        };
+        rule.borrow_mut().running += 1;
+        let result = parser.parse(input);
+        let mut rule = rule.borrow_mut();
+        rule.running -= 1;
+        rule.idle.push(parser);
+        if rule.running == 0 {
+            // the parsers for the levels of nesting this run reached
+            rule.idle.truncate(1);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::SimpleError;
+    use crate::char::char;
+    use crate::combinator::{alt, between, recursive};
+
+    type Rule<'a> = Recursive<'a, &'a str, SimpleError<&'a str>, ()>;
+
+    #[test]
+    fn rules_referring_to_each_other_are_freed() {
+        let alive = Rc::new(());
+        let (a, b) = (Recursive::declare(), Recursive::declare());
+        let (a_handle, b_handle) = (a.handle(), b.handle());
+        let a_guard = alive.clone();
+        let a: Rule = a.define(move || {
+            let _ = &a_guard;
+            alt((between(char('('), b_handle.clone(), char(')')), char('x').map(|_| ())))
+        });
+        let b_guard = alive.clone();
+        let b: Rule = b.define(move || {
+            let _ = &b_guard;
+            alt((between(char('['), a_handle.clone(), char(']')), char('y').map(|_| ())))
+        });
+        assert_eq!(a.clone().parse("([([x])])"), Ok(((), "")));
+        drop((a, b));
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+
+    #[test]
+    fn sibling_rules_live_as_long_as_the_grammar() {
+        let alive = Rc::new(());
+        let guard = alive.clone();
+        let mut a: Rule = {
+            let a = Recursive::declare();
+            let b = a.declare();
+            let (a_handle, b_handle) = (a.handle(), b.handle());
+            b.define(move || {
+                let _ = &guard;
+                alt((between(char('['), a_handle.clone(), char(']')), char('y').map(|_| ())))
+            });
+            a.define(move || alt((between(char('('), b_handle.clone(), char(')')), char('x').map(|_| ()))))
+        };
+        assert_eq!(a.parse("([([x])])"), Ok(((), "")));
+        drop(a);
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+
+    #[test]
+    fn nested_parsers_are_freed_after_a_run() {
+        let mut list: Rule = recursive(|list| between(char('('), alt((list, char('x').map(|_| ()))), char(')')));
+        assert_eq!(list.parse("((((x))))"), Ok(((), "")));
+        assert_eq!(list.rule().unwrap().borrow().idle.len(), 1);
+    }
+
+    #[test]
+    fn self_reference_is_freed() {
+        let alive = Rc::new(());
+        let guard = alive.clone();
+        let mut list: Rule = recursive(move |list| {
+            let _ = &guard;
+            between(char('('), alt((list, char('x').map(|_| ()))), char(')'))
+        });
+        assert_eq!(list.parse("((x))"), Ok(((), "")));
+        drop(list);
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+
+    #[test]
+    fn undefined_and_dropped_rules_fail() {
+        let declared = Recursive::declare();
+        let mut handle: Rule = declared.handle();
+        let err = handle.parse("x").unwrap_err();
+        assert!(err.is_committed());
+        assert_eq!(err.message.as_deref(), Some("a recursive rule that is defined"));
+
+        drop(declared.define(|| char('x').map(|_| ())));
+        let err = handle.parse("x").unwrap_err();
+        assert_eq!(err.message.as_deref(), Some("a recursive rule that is still alive"));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fmt,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+};
+
+/// Where a [`Replay`] pulls its items from.
+pub trait Source {
+    type Item;
+
+    /// Appends the next items to `items`, returning `false` once there are no more.
+    fn fill(&mut self, items: &mut VecDeque<Self::Item>) -> bool;
+}
+
+impl<It> Source for It where It: Iterator {
+    type Item = It::Item;
+
+    fn fill(&mut self, items: &mut VecDeque<Self::Item>) -> bool {
+        match self.next() {
+            Some(item) => {
+                items.push_back(item);
+                true
+            }
+            None => false
+        }
+    }
+}
+
+/// A cursor into items pulled from a [`Source`] on demand, kept in a buffer shared by
+/// every clone so backtracking replays them.
+///
+/// Each cursor holds a checkpoint, the position it was at when it or its original was
+/// last cloned, and the buffer drops the items before the oldest checkpoint. Checkpoints
+/// only move on clone and drop, stepping through the items leaves them alone.
+pub struct Replay<S: Source> {
+    buffer: Rc<RefCell<Buffer<S>>>,
+    pos: usize,
+    mark: Cell<usize>,
+    end: Option<usize>,
+}
+
+struct Buffer<S: Source> {
+    source: S,
+    items: VecDeque<S::Item>,
+    start: usize,
+    done: bool,
+    marks: BTreeMap<usize, usize>,
+}
+
+impl<S> Buffer<S> where S: Source {
+    fn acquire(&mut self, pos: usize) {
+        *self.marks.entry(pos).or_insert(0) += 1;
+    }
+
+    fn release(&mut self, pos: usize) {
+        if let Some(count) = self.marks.get_mut(&pos) {
+            *count -= 1;
+            if *count == 0 {
+                self.marks.remove(&pos);
+            }
+        }
+        let oldest = self.marks.keys().next().copied().unwrap_or(self.start + self.items.len());
+        let n = oldest.saturating_sub(self.start).min(self.items.len());
+        self.items.drain(..n);
+        self.start += n;
+    }
+}
+
+impl<S> Replay<S> where S: Source {
+    pub fn new(source: S) -> Self {
+        let mut buffer = Buffer {
+            source,
+            items: VecDeque::new(),
+            start: 0,
+            done: false,
+            marks: BTreeMap::new(),
+        };
+        buffer.acquire(0);
+        Self {
+            buffer: Rc::new(RefCell::new(buffer)),
+            pos: 0,
+            mark: Cell::new(0),
+            end: None,
+        }
+    }
+
+    /// How many items into the source the cursor is.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// How many items the buffer is holding on to right now.
+    pub fn buffered(&self) -> usize {
+        self.buffer.borrow().items.len()
+    }
+
+    pub fn with_source<T>(&self, f: impl FnOnce(&mut S) -> T) -> T {
+        f(&mut self.buffer.borrow_mut().source)
+    }
+
+    /// This input ending where `other` is.
+    pub fn diff(&self, other: &Self) -> Self {
+        let mut input = self.clone();
+        input.end = Some(self.pos + self.offset_to(other).unwrap_or(0));
+        input
+    }
+
+    pub fn offset_to(&self, other: &Self) -> Option<usize> {
+        if !Rc::ptr_eq(&self.buffer, &other.buffer) {
+            return None;
+        }
+        let offset = other.pos.checked_sub(self.pos)?;
+        self.end.is_none_or(|end| other.pos <= end).then_some(offset)
+    }
+}
+
+impl<S> Replay<S> where S: Source, S::Item: Clone {
+    pub fn peek(&self) -> Option<S::Item> {
+        if self.end.is_some_and(|end| self.pos >= end) {
+            return None;
+        }
+        let mut buffer = self.buffer.borrow_mut();
+        while self.pos >= buffer.start + buffer.items.len() && !buffer.done {
+            let buffer = &mut *buffer;
+            buffer.done = !buffer.source.fill(&mut buffer.items);
+        }
+        let index = self.pos - buffer.start;
+        buffer.items.get(index).cloned()
+    }
+
+    pub fn next(&mut self) -> Option<S::Item> {
+        let item = self.peek()?;
+        self.pos += 1;
+        Some(item)
+    }
+}
+
+impl<S> Clone for Replay<S> where S: Source {
+    fn clone(&self) -> Self {
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.acquire(self.pos);
+        if self.mark.get() != self.pos {
+            buffer.acquire(self.pos);
+            buffer.release(self.mark.replace(self.pos));
+        }
+        Self {
+            buffer: self.buffer.clone(),
+            pos: self.pos,
+            mark: Cell::new(self.pos),
+            end: self.end,
+        }
+    }
+}
+
+impl<S> Drop for Replay<S> where S: Source {
+    fn drop(&mut self) {
+        self.buffer.borrow_mut().release(self.mark.get());
+    }
+}
+
+impl<S> fmt::Debug for Replay<S> where S: Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Replay")
+            .field("pos", &self.pos)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+/// The items of an input from its cursor on.
+#[derive(Debug)]
+pub struct Tokens<S: Source> {
+    input: Replay<S>,
+}
+
+impl<S> Tokens<S> where S: Source {
+    pub fn new(input: Replay<S>) -> Self {
+        Self { input }
+    }
+}
+
+impl<S> Iterator for Tokens<S> where S: Source, S::Item: Clone {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.input.next()
+    }
+}
@@ -0,0 +1,424 @@
+use std::{
+    fmt,
+    ops::Range,
+    fmt::Write,
+};
+
+use crate::{
+    Input,
+    Location,
+    Pos,
+    RichError,
+    SimpleError,
+    ParseError,
+    SourceId,
+};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A location that can be found again in the source text it came from.
+pub trait SourceSpan {
+    /// The byte range in `source` this location covers, empty for a single point.
+    fn source_span(&self, source: &str) -> Range<usize>;
+
+    /// Which of the [`Sources`](crate::Sources) the location is in.
+    fn source(&self) -> SourceId {
+        SourceId::default()
+    }
+
+    /// The line and column the input tracked for the location, if it tracked them.
+    fn line_column(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+impl SourceSpan for Pos {
+    fn source_span(&self, _source: &str) -> Range<usize> {
+        self.offset()..self.offset()
+    }
+
+    fn source(&self) -> SourceId {
+        Pos::source(self)
+    }
+
+    fn line_column(&self) -> Option<(usize, usize)> {
+        self.is_resolved().then(|| (self.line(), self.column()))
+    }
+}
+
+impl SourceSpan for usize {
+    fn source_span(&self, _source: &str) -> Range<usize> {
+        *self..*self
+    }
+}
+
+/// An error that can be shown as a diagnostic by [`Report`].
+pub trait Diagnostic {
+    /// The headline, without the location.
+    fn message(&self) -> String;
+
+    /// The byte range in `source` to point at.
+    fn span(&self, source: &str) -> Range<usize>;
+
+    /// Which of the [`Sources`](crate::Sources) to show.
+    fn source(&self) -> SourceId {
+        SourceId::default()
+    }
+
+    /// Extra lines shown under the snippet.
+    fn notes(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// The line and column to show, if the input tracked them. Otherwise they are
+    /// counted in code points from the span.
+    fn line_column(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+struct Message<'a, I: Location>(&'a RichError<I>);
+
+impl<I> fmt::Display for Message<'_, I>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_message(f, false)
+    }
+}
+
+impl<I> Diagnostic for RichError<I>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: fmt::Display + SourceSpan
+{
+    fn message(&self) -> String {
+        Message(self).to_string()
+    }
+
+    fn span(&self, source: &str) -> Range<usize> {
+        self.location().source_span(source)
+    }
+
+    fn source(&self) -> SourceId {
+        SourceSpan::source(&self.location())
+    }
+
+    fn notes(&self) -> Vec<String> {
+        self.frames().map(|frame| frame.to_string()).collect()
+    }
+
+    fn line_column(&self) -> Option<(usize, usize)> {
+        self.location().line_column()
+    }
+}
+
+impl<I> Diagnostic for SimpleError<I>
+where
+    I: Input + Location,
+    I::Token: fmt::Debug,
+    I::Location: SourceSpan
+{
+    fn message(&self) -> String {
+        if let Some(needed) = self.needed() {
+            return format!("incomplete input, needed {}", needed);
+        }
+        if let Some(reason) = self.exhaustion() {
+            return reason.to_string();
+        }
+        let found = match self.input.clone().peek() {
+            Some(t) => format!("{:?}", t),
+            None => "end of input".to_owned()
+        };
+        match self.message {
+            Some(ref msg) => format!("expected {}, found {}", msg, found),
+            None => format!("unexpected {}", found)
+        }
+    }
+
+    fn span(&self, source: &str) -> Range<usize> {
+        self.input.location().source_span(source)
+    }
+
+    fn source(&self) -> SourceId {
+        SourceSpan::source(&self.input.location())
+    }
+
+    fn line_column(&self) -> Option<(usize, usize)> {
+        self.input.location().line_column()
+    }
+}
+
+impl<D> Diagnostic for Box<D> where D: Diagnostic {
+    fn message(&self) -> String {
+        (**self).message()
+    }
+
+    fn span(&self, source: &str) -> Range<usize> {
+        (**self).span(source)
+    }
+
+    fn source(&self) -> SourceId {
+        (**self).source()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        (**self).notes()
+    }
+
+    fn line_column(&self) -> Option<(usize, usize)> {
+        (**self).line_column()
+    }
+}
+
+/// Renders errors against the source they came from, with the offending line, a gutter
+/// with line numbers and a caret under the location.
+/// # Example
+/// ```
+/// use rtor::{Parser, Report, RichError, State};
+/// use rtor::char::char;
+///
+/// let source = "[a)";
+/// let mut parser = (char('['), char('a'), char(']')).context("list");
+/// let err: RichError<State<&str>> = parser.parse(State::new(source)).unwrap_err();
+///
+/// assert_eq!(Report::new("list.txt", source).render(&err), "\
+/// error: expected ']', found ')'
+///  --> list.txt:1:3
+///   |
+/// 1 | [a)
+///   |   ^
+///   = note: in list at 1:1
+/// ");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Report<'a> {
+    name: &'a str,
+    source: &'a str,
+    color: bool,
+    width: usize,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(name: &'a str, source: &'a str) -> Self {
+        Self {
+            name,
+            source,
+            color: false,
+            width: 100,
+        }
+    }
+
+    /// Turns ANSI colours on or off, they are off by default.
+    pub fn color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    /// Lines wider than `width` columns are cut down to a window around the location,
+    /// with `...` where they were cut. The width is 100 by default, widths below 8 are
+    /// raised to 8 to leave room for the ellipses and the caret.
+    pub fn width(self, width: usize) -> Self {
+        Self { width: width.max(8), ..self }
+    }
+
+    pub fn render<D: Diagnostic>(&self, diagnostic: &D) -> String {
+        let source = self.source;
+        let span = diagnostic.span(source);
+        let start = floor_char_boundary(source, span.start);
+        let end = floor_char_boundary(source, span.end).max(start);
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+        let line = source[line_start..line_end].strip_suffix('\r').unwrap_or(&source[line_start..line_end]);
+        let line_no = source[..line_start].matches('\n').count() + 1;
+        let (line_at, column) = diagnostic.line_column()
+            .unwrap_or_else(|| (line_no, source[line_start..start].chars().count() + 1));
+
+        let (text, caret, len) = self.snippet(line, start - line_start, end.min(line_start + line.len()).saturating_sub(line_start));
+        let gutter = " ".repeat(line_no.to_string().len());
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{}: {}", self.paint(RED, "error"), self.paint(BOLD, &diagnostic.message()));
+        let _ = writeln!(out, "{}{} {}:{}:{}", gutter, self.paint(BLUE, "-->"), self.name, line_at, column);
+        let _ = writeln!(out, "{} {}", gutter, self.paint(BLUE, "|"));
+        let _ = writeln!(out, "{} {} {}", self.paint(BLUE, &line_no.to_string()), self.paint(BLUE, "|"), text);
+        let _ = writeln!(out, "{} {} {}{}", gutter, self.paint(BLUE, "|"), " ".repeat(caret), self.paint(RED, &"^".repeat(len)));
+        for note in diagnostic.notes() {
+            let _ = writeln!(out, "{} {} note: {}", gutter, self.paint(BLUE, "="), note);
+        }
+        out
+    }
+
+    /// Escapes and truncates `line`, returning it with the column and width of the
+    /// underline for the byte range `start..end`.
+    fn snippet(&self, line: &str, start: usize, end: usize) -> (String, usize, usize) {
+        let mut cells = vec![];
+        let mut caret = None;
+        let mut caret_end = None;
+        let mut column = 0;
+        for (i, ch) in line.char_indices() {
+            if i >= start && caret.is_none() {
+                caret = Some(column);
+            }
+            if i >= end && caret_end.is_none() {
+                caret_end = Some(column);
+            }
+            let cell = if ch.is_control() { ch.escape_default().to_string() } else { ch.to_string() };
+            column += cell.chars().count();
+            cells.push(cell);
+        }
+        let total = column;
+        let caret = caret.unwrap_or(total);
+        let len = caret_end.unwrap_or(total).saturating_sub(caret).max(1);
+
+        if total <= self.width {
+            return (cells.concat(), caret, len);
+        }
+
+        let room = self.width - 6;
+        let left = caret.saturating_sub(room / 2).min(total.saturating_sub(room));
+        let right = (left + room).min(total);
+        let mut text = String::new();
+        if left > 0 {
+            text.push_str("...");
+        }
+        let mut column = 0;
+        let mut shown = None;
+        for cell in &cells {
+            let width = cell.chars().count();
+            if column >= left && column + width <= right {
+                shown.get_or_insert(column);
+                text.push_str(cell);
+            }
+            column += width;
+        }
+        if right < total {
+            text.push_str("...");
+        }
+        let offset = if left > 0 { 3 } else { 0 };
+        let len = len.min(right.saturating_sub(caret)).max(1);
+        (text, caret.saturating_sub(shown.unwrap_or(left)) + offset, len)
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct At(Range<usize>);
+
+    impl Diagnostic for At {
+        fn message(&self) -> String {
+            "here".to_owned()
+        }
+
+        fn span(&self, _source: &str) -> Range<usize> {
+            self.0.clone()
+        }
+    }
+
+    /// The location, source line and caret line of the report.
+    fn snippet(report: Report<'_>, span: Range<usize>) -> Vec<String> {
+        let out = report.render(&At(span));
+        let lines: Vec<_> = out.lines().collect();
+        vec![lines[1].to_owned(), lines[3].to_owned(), lines[4].to_owned()]
+    }
+
+    #[test]
+    fn short_line() {
+        let report = Report::new("t", "let x = 1;\nlet y = ;\n");
+        assert_eq!(snippet(report, 19..20), [" --> t:2:9", "2 | let y = ;", "  |         ^"]);
+    }
+
+    #[test]
+    fn long_line_caret_at_start() {
+        let source = format!("x{}", "a".repeat(50));
+        let report = Report::new("t", &source).width(20);
+        assert_eq!(snippet(report, 0..1), [" --> t:1:1", "1 | xaaaaaaaaaaaaa...", "  | ^"]);
+    }
+
+    #[test]
+    fn long_line_caret_in_middle() {
+        let source = format!("{}x{}", "a".repeat(25), "b".repeat(25));
+        let report = Report::new("t", &source).width(20);
+        assert_eq!(snippet(report, 25..26), [" --> t:1:26", "1 | ...aaaaaaaxbbbbbb...", "  |           ^"]);
+    }
+
+    #[test]
+    fn long_line_caret_at_end() {
+        let source = format!("{}x", "a".repeat(50));
+        let report = Report::new("t", &source).width(20);
+        assert_eq!(snippet(report, 50..51), [" --> t:1:51", "1 | ...aaaaaaaaaaaaax", "  |                 ^"]);
+        let report = Report::new("t", &source).width(20);
+        assert_eq!(snippet(report, 51..51), [" --> t:1:52", "1 | ...aaaaaaaaaaaaax", "  |                  ^"]);
+    }
+
+    #[test]
+    fn small_width_is_raised() {
+        let source = "a".repeat(50);
+        let report = Report::new("t", &source).width(0);
+        assert_eq!(snippet(report, 25..26), [" --> t:1:26", "1 | ...aa...", "  |     ^"]);
+    }
+
+    #[test]
+    fn multibyte_chars_are_one_column() {
+        let report = Report::new("t", "é漢字 x");
+        assert_eq!(snippet(report, 9..10), [" --> t:1:5", "1 | é漢字 x", "  |     ^"]);
+        let report = Report::new("t", "é漢字 x");
+        assert_eq!(snippet(report, 3..4), [" --> t:1:2", "1 | é漢字 x", "  |  ^"]);
+    }
+
+    #[test]
+    fn control_chars_are_escaped() {
+        let report = Report::new("t", "\tab\u{7}c");
+        assert_eq!(snippet(report, 4..5), [" --> t:1:5", "1 | \\tab\\u{7}c", "  |          ^"]);
+    }
+
+    #[test]
+    fn eof_after_trailing_newline() {
+        let report = Report::new("t", "ab\r\ncd\n");
+        assert_eq!(snippet(report, 7..7), [" --> t:3:1", "3 | ", "  | ^"]);
+        let report = Report::new("t", "ab\r\ncd");
+        assert_eq!(snippet(report, 6..6), [" --> t:2:3", "2 | cd", "  |   ^"]);
+    }
+
+    #[test]
+    fn location_is_the_tracked_one() {
+        use crate::{Editor, Offset, Parser, State};
+        use crate::char::char;
+
+        let source = "\tx";
+        let err: RichError<State<&str, (), Editor>> = char('\t').andr(char('y')).parse(State::new(source).tracking(Editor::default())).unwrap_err();
+        assert_eq!(Report::new("t", source).render(&err).lines().nth(1), Some(" --> t:1:5"));
+
+        // offsets alone leave the column to be counted
+        let err: RichError<State<&str, (), Offset>> = char('\t').andr(char('y')).parse(State::new(source).tracking(Offset)).unwrap_err();
+        assert_eq!(Report::new("t", source).render(&err).lines().nth(1), Some(" --> t:1:2"));
+    }
+}
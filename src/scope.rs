@@ -0,0 +1,188 @@
+use std::{
+    fmt,
+    sync::Arc,
+};
+
+/// A persistent map for keeping declarations in the user data of a
+/// [`State`](crate::State), like a symbol table or the typedef names of a C parser.
+///
+/// A state's data is cloned the first time it changes after the state was copied for an
+/// alternative, and cloning a scope only copies a pointer, whatever is in it. Inserting
+/// puts a new binding in front of the old ones, which the clones from before don't see,
+/// and a later binding hides an earlier one for the same key.
+///
+/// That makes it a list rather than a table. Lookups walk the bindings, latest first,
+/// so they take time in the number of bindings, and [`len`](Scope::len) counts hidden
+/// bindings too. Nothing is compacted: a hidden binding is only freed once no scope
+/// that can reach it is left. This suits the few hundred names of a grammar rather
+/// than big tables or names that are rebound over and over.
+/// # Example
+/// ```
+/// use rtor::{Parser, ParseResult, State, Scope};
+/// use rtor::char::{char, ascii::multi_alpha1};
+/// use rtor::combinator::{alt, modify_state};
+///
+/// type Input<'a> = State<&'a str, Scope<String, ()>>;
+///
+/// fn typedef(i: Input) -> ParseResult<(), Input> {
+///     let (name, i) = char('#').andr(multi_alpha1).andl(char(';')).parse(i)?;
+///     modify_state(move |names: &mut Scope<String, ()>| names.insert(name.to_string(), ())).parse(i)
+/// }
+///
+/// let mut input = State::with_data(Scope::new(), "#a;#b!");
+/// input = alt((typedef, char('#').map(|_| ()))).parse(input).unwrap().1;
+/// input = alt((typedef, char('#').map(|_| ()))).parse(input).unwrap().1;
+///
+/// // the second typedef backtracked, so only the first one is declared
+/// assert!(input.data.contains_key("a"));
+/// assert!(!input.data.contains_key("b"));
+/// ```
+pub struct Scope<K, V> {
+    head: Option<Arc<Binding<K, V>>>,
+    len: usize,
+}
+
+struct Binding<K, V> {
+    key: K,
+    value: V,
+    next: Option<Arc<Binding<K, V>>>,
+}
+
+impl<K, V> Scope<K, V> {
+    pub fn new() -> Self {
+        Self { head: None, len: 0 }
+    }
+
+    /// Binds `key` to `value`, hiding what it was bound to before.
+    pub fn insert(&mut self, key: K, value: V) {
+        let next = self.head.take();
+        self.head = Some(Arc::new(Binding { key, value, next }));
+        self.len += 1;
+    }
+
+    /// What `key` was bound to last.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized
+    {
+        self.iter().find(|(k, _)| (*k).borrow() == key).map(|(_, v)| v)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized
+    {
+        self.get(key).is_some()
+    }
+
+    /// How many bindings there are, counting hidden ones.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bindings, latest first, hidden ones included.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let mut binding = self.head.as_deref();
+        std::iter::from_fn(move || {
+            let b = binding?;
+            binding = b.next.as_deref();
+            Some((&b.key, &b.value))
+        })
+    }
+}
+
+impl<K, V> Clone for Scope<K, V> {
+    fn clone(&self) -> Self {
+        Self { head: self.head.clone(), len: self.len }
+    }
+}
+
+impl<K, V> Default for Scope<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for Scope<K, V> {
+    fn drop(&mut self) {
+        // unlink the bindings no other scope holds one by one, dropping a long chain
+        // recursively could overflow the stack
+        let mut next = self.head.take();
+        while let Some(binding) = next {
+            next = match Arc::try_unwrap(binding) {
+                Ok(mut binding) => binding.next.take(),
+                Err(_) => None
+            };
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Scope<K, V> where K: fmt::Debug, V: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{Parser, ParseResult, SimpleError, State};
+    use crate::char::char;
+    use crate::combinator::{alt, modify_state};
+
+    static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Big(Vec<u8>);
+
+    impl Clone for Big {
+        fn clone(&self) -> Self {
+            CLONES.fetch_add(1, Ordering::Relaxed);
+            Big(self.0.clone())
+        }
+    }
+
+    type Input<'a> = State<&'a str, Scope<u32, Big>>;
+
+    fn declare(i: Input) -> ParseResult<char, Input, Box<SimpleError<Input>>> {
+        char('x').andl(modify_state(|s: &mut Scope<u32, Big>| s.insert(1, Big(vec![0; 1 << 20])))).andl(char('!')).parse(i)
+    }
+
+    #[test]
+    fn backtracking_does_not_clone_values() {
+        let mut scope = Scope::new();
+        scope.insert(0, Big(vec![0; 1 << 20]));
+        let mut parser = alt((declare, declare, char('x')));
+        let (_, rest) = parser.parse(State::with_data(scope, "x?")).unwrap();
+        assert_eq!(CLONES.load(Ordering::Relaxed), 0);
+        assert_eq!(rest.data.len(), 1);
+        assert!(rest.data.get(&1).is_none());
+    }
+
+    #[test]
+    fn later_bindings_hide_earlier_ones() {
+        let mut a = Scope::new();
+        a.insert("x", 1);
+        let mut b = a.clone();
+        b.insert("x", 2);
+        assert_eq!((a.get("x"), b.get("x")), (Some(&1), Some(&2)));
+        assert_eq!(b.iter().map(|(_, v)| *v).collect::<Vec<_>>(), [2, 1]);
+    }
+
+    #[test]
+    fn long_scopes_drop() {
+        let mut scope = Scope::new();
+        for i in 0..1_000_000 {
+            scope.insert(i, ());
+        }
+        drop(scope);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    fmt,
+    ops::{Deref, Range},
+};
+
+use crate::{
+    Input,
+    Location,
+    AsChar,
+};
+
+/// Text or a slice of tokens that a [`Shared`] input can be a window into.
+pub trait Slice {
+    type Token: Clone;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The part of the slice in `range`, which is in bounds and on a boundary.
+    fn get(&self, range: Range<usize>) -> &Self;
+
+    /// The first token and how long it is.
+    fn first_token(&self) -> Option<(Self::Token, usize)>;
+
+    /// How many bytes the tokens would take up as UTF-8.
+    fn len_utf8(&self) -> usize where Self::Token: AsChar {
+        let mut len = 0;
+        let mut offset = 0;
+        while let Some((t, n)) = self.get(offset..self.len()).first_token() {
+            len += t.len_utf8();
+            offset += n;
+        }
+        len
+    }
+
+    /// Whether the slice can be split at `offset`.
+    fn is_boundary(&self, offset: usize) -> bool {
+        offset <= self.len()
+    }
+
+    /// The length of `literal` if the slice starts with it.
+    fn prefix(&self, literal: &str) -> Option<usize> where Self::Token: AsChar {
+        let mut offset = 0;
+        for ch in literal.chars() {
+            match self.get(offset..self.len()).first_token() {
+                Some((t, n)) if t.as_char() == ch => offset += n,
+                _ => return None
+            }
+        }
+        Some(offset)
+    }
+}
+
+impl Slice for str {
+    type Token = char;
+
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    fn len_utf8(&self) -> usize {
+        str::len(self)
+    }
+
+    fn get(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+
+    fn first_token(&self) -> Option<(Self::Token, usize)> {
+        let ch = self.chars().next()?;
+        Some((ch, ch.len_utf8()))
+    }
+
+    fn is_boundary(&self, offset: usize) -> bool {
+        self.is_char_boundary(offset)
+    }
+
+    fn prefix(&self, literal: &str) -> Option<usize> {
+        self.starts_with(literal).then_some(literal.len())
+    }
+}
+
+impl<T: Clone> Slice for [T] {
+    type Token = T;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn get(&self, range: Range<usize>) -> &Self {
+        &self[range]
+    }
+
+    fn first_token(&self) -> Option<(Self::Token, usize)> {
+        Some((self.first()?.clone(), 1))
+    }
+}
+
+/// An input over a reference counted buffer such as `Rc<str>`, `Arc<str>` or `Arc<[u8]>`.
+///
+/// It is a window into the buffer, so cloning and slicing it only bumps the count. What
+/// `recognize`, `string` or `take_while` return from it owns its data and can outlive
+/// the parse, and with `Arc` it can be sent to another thread. Its location is the
+/// offset into the buffer.
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use rtor::{Parser, ParseResult, Shared};
+/// use rtor::char::{char, ascii::multi_alpha1};
+/// use rtor::combinator::sep_by;
+///
+/// type Input = Shared<Arc<str>>;
+///
+/// fn words(i: Input) -> ParseResult<Vec<Input>, Input> {
+///     sep_by(multi_alpha1, char(' ')).parse(i)
+/// }
+///
+/// let (words, _) = words(Shared::new(String::from("hello shared world"))).unwrap();
+/// let handle = std::thread::spawn(move || words.iter().map(|w| w.len()).sum::<usize>());
+///
+/// assert_eq!(handle.join().unwrap(), 16);
+/// ```
+#[derive(Clone)]
+pub struct Shared<P> {
+    buffer: P,
+    range: Range<usize>,
+}
+
+impl<P> Shared<P> where P: Deref, P::Target: Slice {
+    pub fn new<B: Into<P>>(buffer: B) -> Self {
+        let buffer = buffer.into();
+        let len = buffer.len();
+        Self {
+            buffer,
+            range: 0..len,
+        }
+    }
+
+    /// The whole buffer this input is a window into.
+    pub fn buffer(&self) -> &P {
+        &self.buffer
+    }
+
+    /// Where the window is in the buffer.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    fn window(&self, range: Range<usize>) -> Self where P: Clone {
+        Self {
+            buffer: self.buffer.clone(),
+            range,
+        }
+    }
+}
+
+impl<P> Deref for Shared<P> where P: Deref, P::Target: Slice {
+    type Target = P::Target;
+
+    fn deref(&self) -> &Self::Target {
+        self.buffer.get(self.range.clone())
+    }
+}
+
+impl<P> PartialEq for Shared<P> where P: Deref, P::Target: Slice + PartialEq {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<P> fmt::Debug for Shared<P> where P: Deref, P::Target: Slice + fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<P> fmt::Display for Shared<P> where P: Deref, P::Target: Slice + fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<P> Input for Shared<P> where P: Clone + Deref, P::Target: Slice {
+    type Token = <P::Target as Slice>::Token;
+    type Tokens = Tokens<P>;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        let (t, n) = self.first_token()?;
+        self.range.start += n;
+        Some(t)
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        Some(self.first_token()?.0)
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        let offset = self.offset_to(other).unwrap_or(0);
+        self.window(self.range.start..self.range.start + offset)
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        Tokens { input: self.clone() }
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        let same = std::ptr::addr_eq(&*self.buffer as *const P::Target, &*other.buffer as *const P::Target);
+        let offset = other.range.start.checked_sub(self.range.start)?;
+        (same && other.range.start <= self.range.end).then_some(offset)
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> where Self::Token: AsChar {
+        self.prefix(literal)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let mut offset = offset.min(self.len());
+        while !self.is_boundary(offset) {
+            offset -= 1;
+        }
+        let mid = self.range.start + offset;
+        (self.window(self.range.start..mid), self.window(mid..self.range.end))
+    }
+
+    fn split_at_position<F>(&self, mut pred: F) -> (Self, Self) where F: FnMut(&Self::Token) -> bool {
+        let slice: &P::Target = self;
+        let mut offset = 0;
+        while let Some((t, n)) = slice.get(offset..slice.len()).first_token() {
+            if pred(&t) {
+                break;
+            }
+            offset += n;
+        }
+        self.split_at(offset)
+    }
+
+    fn len_utf8(&self) -> usize where Self::Token: AsChar {
+        Slice::len_utf8(&**self)
+    }
+}
+
+impl<P> Location for Shared<P> {
+    type Location = usize;
+
+    fn location(&self) -> Self::Location {
+        self.range.start
+    }
+}
+
+/// The tokens of a [`Shared`] input.
+pub struct Tokens<P> {
+    input: Shared<P>,
+}
+
+impl<P> Iterator for Tokens<P> where P: Clone + Deref, P::Target: Slice {
+    type Item = <P::Target as Slice>::Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.input.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn split_inside_a_char_splits_before_it() {
+        let input: Shared<Rc<str>> = Shared::new("héllo");
+        let (head, rest) = input.split_at(2);
+        assert_eq!((&*head, &*rest, rest.location()), ("h", "éllo", 1));
+
+        let (head, rest) = input.split_at(9);
+        assert_eq!((&*head, &*rest), ("héllo", ""));
+    }
+
+    #[test]
+    fn split_past_the_end_of_a_slice() {
+        let input: Shared<Rc<[u8]>> = Shared::new(&b"ab"[..]);
+        let (head, rest) = input.split_at(3);
+        assert_eq!((&*head, &*rest, rest.location()), (&b"ab"[..], &b""[..], 2));
+    }
+}
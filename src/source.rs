@@ -0,0 +1,112 @@
+use std::{
+    fmt,
+    rc::Rc,
+};
+
+use crate::{
+    Diagnostic,
+    Report,
+    Shared,
+    State,
+};
+
+/// Which of the [`Sources`] a position or span is in. The default is the first source
+/// added, so inputs that only ever have one source don't need to set it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceId(usize);
+
+impl SourceId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for SourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The names and text of every file a parse reads, so a position, which only holds a
+/// [`SourceId`], can be shown against the right one.
+///
+/// The text is reference counted, so [`input`](Sources::input) can hand out inputs
+/// that outlive a borrow of the registry and more files can be added mid-parse, as an
+/// include is found.
+/// # Example
+/// ```
+/// use rtor::{Parser, RichError, Sources, State, Shared};
+/// use rtor::char::string;
+/// use std::rc::Rc;
+///
+/// let mut sources = Sources::new();
+/// let main = sources.add("main.c", "int a;");
+/// let header = sources.add("defs.h", "#define A\nint b = ;");
+///
+/// let input = sources.input(header);
+/// let err: RichError<State<Shared<Rc<str>>>> = string("#define A\nint b = 1").parse(input).unwrap_err();
+///
+/// assert_eq!(sources.find("main.c"), Some(main));
+/// assert_eq!(sources.render(&err), "\
+/// error: expected \"#define A\\nint b = 1\", found ';'
+///  --> defs.h:2:9
+///   |
+/// 2 | int b = ;
+///   |         ^
+/// ");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    files: Vec<(String, Rc<str>)>,
+}
+
+impl Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<N: Into<String>, T: Into<Rc<str>>>(&mut self, name: N, text: T) -> SourceId {
+        self.files.push((name.into(), text.into()));
+        SourceId(self.files.len() - 1)
+    }
+
+    /// The first source added under `name`.
+    pub fn find(&self, name: &str) -> Option<SourceId> {
+        self.files.iter().position(|(n, _)| n == name).map(SourceId)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// # Panics
+    /// If `id` is not from this registry.
+    pub fn name(&self, id: SourceId) -> &str {
+        &self.files[id.0].0
+    }
+
+    /// # Panics
+    /// If `id` is not from this registry.
+    pub fn text(&self, id: SourceId) -> &Rc<str> {
+        &self.files[id.0].1
+    }
+
+    /// An input over the source `id` whose positions are in it.
+    pub fn input(&self, id: SourceId) -> State<Shared<Rc<str>>> {
+        State::new(Shared::<Rc<str>>::new(self.text(id).clone())).in_source(id)
+    }
+
+    /// A [`Report`] for the source `id`.
+    pub fn report(&self, id: SourceId) -> Report<'_> {
+        Report::new(self.name(id), self.text(id))
+    }
+
+    /// Renders `diagnostic` against the source it points into.
+    pub fn render<D: Diagnostic>(&self, diagnostic: &D) -> String {
+        self.report(diagnostic.source()).render(diagnostic)
+    }
+}
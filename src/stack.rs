@@ -0,0 +1,335 @@
+use std::{
+    mem,
+    rc::Rc,
+    ops::Deref,
+    cmp::Ordering,
+};
+
+use crate::{
+    Input,
+    Location,
+    AsChar,
+    Exhausted,
+};
+
+/// An input that other inputs can be spliced into mid-parse, for `#include`-style
+/// directives.
+///
+/// [`include`](InputStack::include) makes another input the current one and parsing
+/// carries on in the including input once it runs out. An included input ends like a
+/// file does, a literal, a `take_while` or a [`recognize`](crate::combinator::recognize)
+/// never runs across where it starts or stops. The stack is shared between clones, so
+/// backtracking past an include undoes it. Its location is the current input's.
+/// # Example
+/// ```
+/// use std::rc::Rc;
+/// use rtor::{Parser, ParseResult, ParseError, RichError, Sources, InputStack, State, Shared};
+/// use rtor::char::{char, ascii::{multi_alpha1, multi_space}};
+/// use rtor::combinator::{many, opt, eof};
+///
+/// type Input = InputStack<State<Shared<Rc<str>>>>;
+///
+/// fn include(sources: &Sources) -> impl FnMut(Input) -> ParseResult<(), Input, RichError<Input>> + '_ {
+///     move |i| {
+///         let (name, mut i) = multi_space.andr(char('@')).andr(multi_alpha1).parse(i)?;
+///         match sources.find(&name.to_string()) {
+///             Some(id) => i.include(sources.input(id)),
+///             None => return Err(RichError::expect("a known file".into(), name))
+///         }
+///         Ok(((), i))
+///     }
+/// }
+///
+/// fn words(sources: &Sources) -> impl Parser<Input, RichError<Input>, Output = Vec<String>> + '_ {
+///     many(opt(include(sources)).andr(multi_space).andr(multi_alpha1).map(|w: Input| w.to_string()))
+///         .andl(multi_space)
+///         .andl(eof)
+/// }
+///
+/// let mut sources = Sources::new();
+/// let main = sources.add("main", "a @lib d");
+/// sources.add("lib", "b\nc");
+///
+/// let (output, _) = words(&sources).parse(InputStack::new(sources.input(main))).unwrap();
+/// assert_eq!(output, ["a", "b", "c", "d"]);
+///
+/// sources.add("bad", "e\nf ?");
+/// let main = sources.add("main2", "a @bad d");
+/// let err = words(&sources).parse(InputStack::new(sources.input(main))).unwrap_err();
+/// assert!(sources.render(&err).contains(" --> bad:2:3\n"));
+/// ```
+#[derive(Debug)]
+pub struct InputStack<I> {
+    input: I,
+    parent: Option<Rc<InputStack<I>>>,
+}
+
+impl<I> InputStack<I> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            parent: None,
+        }
+    }
+
+    /// Splices `input` in, it is parsed before the rest of the current one.
+    pub fn include(&mut self, input: I) {
+        let parent = mem::replace(self, InputStack::new(input));
+        self.parent = Some(Rc::new(parent));
+    }
+
+    /// How many includes deep the current input is, to stop runaway recursive includes.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut parent = &self.parent;
+        while let Some(p) = parent {
+            depth += 1;
+            parent = &p.parent;
+        }
+        depth
+    }
+
+    /// Whether `other` is in the same input as this one.
+    fn same_input(&self, other: &Self) -> bool {
+        match (&self.parent, &other.parent) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false
+        }
+    }
+}
+
+impl<I> InputStack<I> where I: Input {
+    /// Goes back to the including input for as long as the current one has run out.
+    fn settle(&mut self) {
+        while self.parent.is_some() && self.input.peek().is_none() {
+            if let Some(parent) = self.parent.take() {
+                *self = (*parent).clone();
+            }
+        }
+    }
+
+    fn settled(&self) -> Self {
+        let mut input = self.clone();
+        input.settle();
+        input
+    }
+
+    fn split(&self, head: I, rest: I) -> (Self, Self) {
+        (
+            InputStack::new(head),
+            InputStack { input: rest, parent: self.parent.clone() }
+        )
+    }
+}
+
+impl<I> Clone for InputStack<I> where I: Clone {
+    fn clone(&self) -> Self {
+        Self {
+            input: self.input.clone(),
+            parent: self.parent.clone(),
+        }
+    }
+}
+
+impl<I> Deref for InputStack<I> {
+    type Target = I;
+
+    fn deref(&self) -> &Self::Target {
+        &self.input
+    }
+}
+
+impl<I> Input for InputStack<I> where I: Input {
+    type Token = I::Token;
+    type Tokens = Tokens<I>;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        self.settle();
+        self.input.next()
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        self.settle();
+        self.input.peek()
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        let (this, other) = (self.settled(), other.settled());
+        if this.same_input(&other) {
+            InputStack::new(this.input.diff(&other.input))
+        } else {
+            InputStack::new(this.input.diff(&this.input))
+        }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        Tokens { input: self.clone() }
+    }
+
+    fn is_partial(&self) -> bool {
+        self.settled().input.is_partial()
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        let (this, other) = (self.settled(), other.settled());
+        if this.same_input(&other) {
+            this.input.offset_to(&other.input)
+        } else {
+            None
+        }
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> where Self::Token: AsChar {
+        self.settled().input.starts_with(literal)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let this = self.settled();
+        let (head, rest) = this.input.split_at(offset);
+        this.split(head, rest)
+    }
+
+    fn split_at_position<P>(&self, pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let this = self.settled();
+        let (head, rest) = this.input.split_at_position(pred);
+        this.split(head, rest)
+    }
+
+    fn position<P>(&self, pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        self.settled().input.position(pred)
+    }
+
+    fn charge(&self, cost: usize) -> Result<(), Exhausted> {
+        self.input.charge(cost)
+    }
+}
+
+impl<I> Location for InputStack<I> where I: Input + Location {
+    type Location = I::Location;
+
+    fn location(&self) -> Self::Location {
+        self.settled().input.location()
+    }
+
+    fn progress(&self, other: &Self) -> Option<Ordering> {
+        self.settled().input.progress(&other.settled().input)
+    }
+}
+
+/// The tokens of an [`InputStack`], running on into the including inputs.
+#[derive(Debug)]
+pub struct Tokens<I> {
+    input: InputStack<I>,
+}
+
+impl<I> Iterator for Tokens<I> where I: Input {
+    type Item = I::Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.input.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{ParseError, RichError, Sources, State, Shared};
+
+    type Text = InputStack<State<Shared<Rc<str>>>>;
+
+    /// The files `texts` registered in one place, and an input for each.
+    fn files(texts: &[&str]) -> (Sources, Vec<State<Shared<Rc<str>>>>) {
+        let mut sources = Sources::new();
+        let ids: Vec<_> = texts.iter().enumerate().map(|(n, text)| sources.add(format!("file{}", n), *text)).collect();
+        let inputs = ids.into_iter().map(|id| sources.input(id)).collect();
+        (sources, inputs)
+    }
+
+    fn rest(mut input: Text) -> String {
+        let mut out = String::new();
+        while let Some(ch) = input.next() {
+            out.push(ch);
+        }
+        out
+    }
+
+    #[test]
+    fn include_is_parsed_before_the_rest() {
+        let (_sources, files) = files(&["ad", "bc"]);
+        let mut input = InputStack::new(files[0].clone());
+        input.next();
+        input.include(files[1].clone());
+        assert_eq!(input.depth(), 1);
+        assert_eq!(rest(input), "bcd");
+    }
+
+    #[test]
+    fn backtracking_past_an_include_undoes_it() {
+        let (_sources, files) = files(&["ab", "x"]);
+        let before = InputStack::new(files[0].clone());
+        let mut after = before.clone();
+        after.include(files[1].clone());
+        assert_eq!((rest(before), rest(after)), ("ab".to_owned(), "xab".to_owned()));
+    }
+
+    #[test]
+    fn diff_stops_at_the_include() {
+        let (_sources, files) = files(&["ab", "xy"]);
+        let mut start = InputStack::new(files[0].clone());
+        start.include(files[1].clone());
+        let mut end = start.clone();
+        end.next();
+        assert_eq!(start.offset_to(&end), Some(1));
+        assert_eq!(rest(start.diff(&end)), "x");
+
+        end.next();
+        end.next();
+        assert_eq!(start.offset_to(&end), None);
+        assert_eq!(rest(start.diff(&end)), "");
+    }
+
+    #[test]
+    fn empty_include_falls_through() {
+        let (_sources, files) = files(&["ab", ""]);
+        let mut input = InputStack::new(files[0].clone());
+        input.include(files[1].clone());
+        assert_eq!(input.peek(), Some('a'));
+        assert_eq!(input.split_at(1).0.to_string(), "a");
+    }
+
+    #[test]
+    fn finished_include_is_located_in_the_includer() {
+        let (sources, files) = files(&["ab", "x"]);
+        let mut input = InputStack::new(files[0].clone());
+        input.next();
+        input.include(files[1].clone());
+        input.next();
+        assert_eq!(input.location().source(), sources.find("file0").unwrap());
+        assert_eq!(input.location().column(), 2);
+        assert!(!input.is_partial());
+    }
+
+    #[test]
+    fn errors_in_different_sources_do_not_rank_by_id() {
+        let (sources, files) = files(&["abcdef", "x"]);
+        let mut far = InputStack::new(files[0].clone());
+        for _ in 0..5 {
+            far.next();
+        }
+        let mut near = InputStack::new(files[0].clone());
+        near.include(files[1].clone());
+        assert_eq!(near.location().source(), sources.find("file1").unwrap());
+
+        let merged = RichError::unexpect(far.clone()).merge(RichError::unexpect(near.clone()));
+        assert_eq!(merged.location(), near.location());
+        let merged = RichError::unexpect(near).merge(RichError::unexpect(far.clone()));
+        assert_eq!(merged.location(), far.location());
+
+        let start = InputStack::new(files[0].clone());
+        let merged = RichError::unexpect(far.clone()).merge(RichError::unexpect(start));
+        assert_eq!(merged.location(), far.location());
+    }
+}
@@ -1,34 +1,194 @@
-use std::ops::Deref;
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    cmp::Ordering,
+    sync::Arc,
+};
 
 use crate::{
     Input,
+    Location,
     AsChar,
+    SourceId,
+    Memoize,
+    Budget,
+    Exhausted,
+    indent::Layout,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// How a [`State`] moves its [`Pos`] over a token.
+pub trait Track: Clone {
+    fn advance<T: AsChar>(&self, pos: &mut Pos, token: &T);
+
+    /// Moves `pos` over all of `tokens` at once, for the bulk operations of the input. Walks
+    /// the tokens unless the policy has a shortcut.
+    fn advance_over<I>(&self, pos: &mut Pos, tokens: &I) where I: Input, I::Token: AsChar {
+        for t in tokens.tokens() {
+            self.advance(pos, &t);
+        }
+    }
+
+    /// The position to carry on from when a state switches to this policy at `pos`.
+    fn init(&self, pos: Pos) -> Pos {
+        pos
+    }
+}
+
+/// One column per token and a new line after `'\n'`, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PerToken;
+
+impl Track for PerToken {
+    fn advance<T: AsChar>(&self, pos: &mut Pos, token: &T) {
+        match token.as_char() {
+            '\n' => pos.newline(1),
+            _ => pos.forward(1, token.len_utf8())
+        }
+    }
+}
+
+/// Only the byte offset, so advancing costs next to nothing. Lines and columns are left
+/// unresolved until a [`LineIndex`](crate::LineIndex) works them out, which is only
+/// needed when an error is reported.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Offset;
+
+impl Track for Offset {
+    fn advance<T: AsChar>(&self, pos: &mut Pos, token: &T) {
+        pos.offset += token.len_utf8();
+    }
+
+    fn advance_over<I>(&self, pos: &mut Pos, tokens: &I) where I: Input, I::Token: AsChar {
+        pos.offset += tokens.len_utf8();
+    }
+
+    fn init(&self, pos: Pos) -> Pos {
+        Pos::at(pos.offset).in_source(pos.source)
+    }
+}
+
+/// Columns as a text editor shows them: tabs jump to the next multiple of `tab_width`,
+/// `"\r\n"` is a single line break and columns count code points, also when the input
+/// is UTF-8 bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Editor {
+    pub tab_width: usize,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self { tab_width: 4 }
+    }
+}
+
+impl Track for Editor {
+    fn advance<T: AsChar>(&self, pos: &mut Pos, token: &T) {
+        match token.as_char() {
+            '\n' => pos.newline(1),
+            '\r' => pos.forward(0, 1),
+            '\t' => {
+                let width = self.tab_width.max(1);
+                // column 0 is a position an offset-only policy left unresolved
+                pos.forward(width - pos.column.saturating_sub(1) % width, 1)
+            }
+            _ => pos.forward(token.len_chars(), token.len_utf8())
+        }
+    }
+}
+
+/// Columns in UTF-16 code units, as the language server protocol counts them, and
+/// `"\r\n"` as a single line break.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Utf16;
+
+impl Track for Utf16 {
+    fn advance<T: AsChar>(&self, pos: &mut Pos, token: &T) {
+        match token.as_char() {
+            '\n' => pos.newline(1),
+            '\r' => pos.forward(0, 1),
+            _ => pos.forward(token.len_utf16(), token.len_utf8())
+        }
+    }
+}
+
+/// A line, column and byte offset in the source. Lines and columns start at 1, what a
+/// column counts depends on the [`Track`] policy of the [`State`].
+///
+/// With several sources it also says which one, see [`Sources`](crate::Sources).
+/// Positions in different sources sort by their [`SourceId`] first, but that says
+/// nothing about how far a parse got, see [`Location::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Pos {
+    source: SourceId,
     line: usize,
     column: usize,
+    offset: usize,
+}
+
+impl Default for Pos {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Pos {
     pub fn new() -> Self {
         Self {
+            source: SourceId::default(),
             line: 1,
             column: 1,
+            offset: 0,
         }
     }
 
+    /// A position known only by its byte offset, its line and column are 0 until it is
+    /// [resolved](crate::LineIndex::resolve).
+    pub fn at(offset: usize) -> Self {
+        Self {
+            source: SourceId::default(),
+            line: 0,
+            column: 0,
+            offset,
+        }
+    }
+
+    pub(crate) fn from_parts(line: usize, column: usize, offset: usize) -> Self {
+        Self { source: SourceId::default(), line, column, offset }
+    }
+
+    /// This position in the source `source`.
+    pub fn in_source(self, source: SourceId) -> Self {
+        Self { source, ..self }
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.line != 0
+    }
+
     pub fn advance(&mut self, ch: char) {
         match ch {
-            '\n' => {
-                self.line += 1; 
-                self.column = 1;
-            }
-            _ => self.column += 1
+            '\n' => self.newline(1),
+            _ => self.forward(1, ch.len_utf8())
         }
     }
 
+    /// Moves `columns` to the right over `bytes` of source.
+    pub fn forward(&mut self, columns: usize, bytes: usize) {
+        self.column += columns;
+        self.offset += bytes;
+    }
+
+    /// Moves to the start of the next line over a line break `bytes` long.
+    pub fn newline(&mut self, bytes: usize) {
+        self.line += 1;
+        self.column = 1;
+        self.offset += bytes;
+    }
+
+    pub fn source(&self) -> SourceId {
+        self.source
+    }
+
     pub fn line(&self) -> usize {
         self.line
     }
@@ -36,13 +196,108 @@ impl Pos {
     pub fn column(&self) -> usize {
         self.column
     }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 }
 
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_resolved() {
+            write!(f, "{}:{}", self.line, self.column)
+        } else {
+            write!(f, "offset {}", self.offset)
+        }
+    }
+}
+
+/// An input that knows its [`Pos`] and carries user data along.
+///
+/// The data travels with the input, so when an alternative backtracks to an earlier
+/// input the data goes back to what it was there too. The copies of the input share it
+/// through a [`StateData`], and it is only cloned when one of them changes it, so data
+/// that changes often as it grows should be cheap to clone, like a
+/// [`Scope`](crate::Scope).
 #[derive(Debug, Clone)]
-pub struct State<I, Data = ()> {
+pub struct State<I, Data = (), T = PerToken> {
     input: I,
     pos: Pos,
-    pub data: Data,
+    reference: Pos,
+    depth: usize,
+    budget: Option<Budget>,
+    /// Where a recovery last consumed nothing and how many errors there were then.
+    pub(crate) stalled: Option<(Pos, usize)>,
+    track: T,
+    pub data: StateData<Data>,
+}
+
+/// The user data of a [`State`], shared by the copies of the state and cloned the first
+/// time one of them changes it. It derefs to the data, mutably too.
+/// # Example
+/// ```
+/// use rtor::State;
+///
+/// let mut state = State::with_data(vec![1], "");
+/// let copy = state.clone();
+/// state.data.push(2);
+/// assert_eq!((&*state.data, &*copy.data), (&vec![1, 2], &vec![1]));
+/// ```
+pub struct StateData<Data>(Arc<Data>);
+
+impl<Data> StateData<Data> {
+    pub fn new(data: Data) -> Self {
+        Self(Arc::new(data))
+    }
+
+    /// The data, cloned if other states still share it.
+    pub fn into_inner(self) -> Data where Data: Clone {
+        Arc::unwrap_or_clone(self.0)
+    }
+}
+
+impl<Data> Deref for StateData<Data> {
+    type Target = Data;
+
+    fn deref(&self) -> &Data {
+        &self.0
+    }
+}
+
+impl<Data: Clone> DerefMut for StateData<Data> {
+    fn deref_mut(&mut self) -> &mut Data {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<Data> Clone for StateData<Data> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Data: Default> Default for StateData<Data> {
+    fn default() -> Self {
+        Self::new(Data::default())
+    }
+}
+
+impl<Data> From<Data> for StateData<Data> {
+    fn from(data: Data) -> Self {
+        Self::new(data)
+    }
+}
+
+impl<Data: PartialEq> PartialEq for StateData<Data> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<Data: fmt::Debug> fmt::Debug for StateData<Data> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
 impl<I> State<I> {
@@ -50,7 +305,12 @@ impl<I> State<I> {
         Self {
             input,
             pos: Pos::new(),
-            data: ()
+            reference: Pos::new(),
+            depth: 0,
+            budget: None,
+            stalled: None,
+            track: PerToken,
+            data: StateData::new(())
         }
     }
 }
@@ -60,16 +320,114 @@ impl<I, Data> State<I, Data> {
         Self {
             input,
             pos: Pos::new(),
-            data
+            reference: Pos::new(),
+            depth: 0,
+            budget: None,
+            stalled: None,
+            track: PerToken,
+            data: StateData::new(data)
         }
     }
+}
 
+impl<I, Data, T> State<I, Data, T> {
     pub fn pos(&self) -> Pos {
         self.pos
     }
+
+    /// Tracks positions with `track` instead.
+    /// # Example
+    /// ```
+    /// use rtor::{Input, State, Editor, Utf16};
+    ///
+    /// let mut state = State::new("\tx\r\n😀y").tracking(Editor { tab_width: 4 });
+    /// state.next();
+    /// assert_eq!(state.pos().column(), 5);
+    ///
+    /// let mut state = State::new("😀y".as_bytes()).tracking(Utf16);
+    /// while state.next() != Some(b'y') {}
+    /// assert_eq!((state.pos().column(), state.pos().offset()), (4, 5));
+    /// ```
+    pub fn tracking<U: Track>(self, track: U) -> State<I, Data, U> {
+        State {
+            input: self.input,
+            pos: track.init(self.pos),
+            reference: track.init(self.reference),
+            depth: self.depth,
+            budget: self.budget,
+            stalled: self.stalled,
+            track,
+            data: self.data
+        }
+    }
+
+    /// Charges `budget` for every alternative tried on this input and the inputs that
+    /// follow from it, see [`Input::charge`].
+    pub fn metering(self, budget: &Budget) -> Self {
+        State {
+            budget: Some(budget.clone()),
+            ..self
+        }
+    }
+
+    /// Positions this state in the source `source`, for inputs registered with
+    /// [`Sources`](crate::Sources).
+    pub fn in_source(self, source: SourceId) -> Self {
+        State {
+            pos: self.pos.in_source(source),
+            reference: self.reference.in_source(source),
+            ..self
+        }
+    }
+}
+
+/// An input that carries user data, which the state combinators like
+/// [`modify_state`](crate::combinator::modify_state) work on.
+pub trait UserState {
+    type Data;
+
+    fn data(&self) -> &Self::Data;
+
+    fn data_mut(&mut self) -> &mut Self::Data;
+
+    fn set_data(&mut self, data: Self::Data);
+}
+
+impl<I, Data, T> UserState for State<I, Data, T> where Data: Clone {
+    type Data = Data;
+
+    fn data(&self) -> &Data {
+        &self.data
+    }
+
+    fn data_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+
+    fn set_data(&mut self, data: Data) {
+        self.data = StateData::new(data);
+    }
+}
+
+/// An input that counts how deeply parsers are nested, for
+/// [`limit_depth`](crate::combinator::limit_depth).
+pub trait Depth {
+    fn depth(&self) -> usize;
+
+    fn set_depth(&mut self, depth: usize);
+}
+
+impl<I, Data, T> Depth for State<I, Data, T> {
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+    }
 }
 
-impl<I, Data> Deref for State<I, Data> {
+impl<I, Data, T> Deref for State<I, Data, T> {
     type Target = I;
 
     fn deref(&self) -> &Self::Target {
@@ -77,18 +435,19 @@ impl<I, Data> Deref for State<I, Data> {
     }
 }
 
-impl<I, Data> Input for State<I, Data> 
+impl<I, Data, T> Input for State<I, Data, T> 
 where 
     I: Input,
     I::Token: AsChar,
-    Data: Clone
+    Data: Clone,
+    T: Track
 {
     type Token = I::Token;
     type Tokens = I::Tokens;
 
     fn next(&mut self) -> Option<Self::Token> {
         let t = self.input.next()?;
-        self.pos.advance(t.as_char());
+        self.track.advance(&mut self.pos, &t);
         Some(t)
     }
 
@@ -100,6 +459,11 @@ where
         State { 
             input: self.input.diff(&other.input), 
             pos: self.pos,
+            reference: self.reference,
+            depth: self.depth,
+            budget: self.budget.clone(),
+            stalled: self.stalled,
+            track: self.track.clone(),
             data: self.data.clone()
         }
     }
@@ -107,4 +471,200 @@ where
     fn tokens(&self) -> Self::Tokens {
         self.input.tokens()
     }
-}
\ No newline at end of file
+
+    fn is_partial(&self) -> bool {
+        self.input.is_partial()
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        self.input.offset_to(&other.input)
+    }
+
+    fn starts_with(&self, literal: &str) -> Option<usize> {
+        self.input.starts_with(literal)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let (head, rest) = self.input.split_at(offset);
+        self.split(head, rest)
+    }
+
+    fn split_at_position<P>(&self, pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let (head, rest) = self.input.split_at_position(pred);
+        self.split(head, rest)
+    }
+
+    fn position<P>(&self, pred: P) -> Option<usize> where P: FnMut(&Self::Token) -> bool {
+        self.input.position(pred)
+    }
+
+    fn len_utf8(&self) -> usize {
+        self.input.len_utf8()
+    }
+
+    fn charge(&self, cost: usize) -> Result<(), Exhausted> {
+        match &self.budget {
+            Some(budget) => budget.charge(cost),
+            None => self.input.charge(cost)
+        }
+    }
+}
+
+impl<I, Data, T> State<I, Data, T>
+where
+    I: Input,
+    I::Token: AsChar,
+    Data: Clone,
+    T: Track
+{
+    fn split(&self, head: I, rest: I) -> (Self, Self) {
+        let mut pos = self.pos;
+        self.track.advance_over(&mut pos, &head);
+        (
+            State { input: head, pos: self.pos, reference: self.reference, depth: self.depth, budget: self.budget.clone(), stalled: self.stalled, track: self.track.clone(), data: self.data.clone() },
+            State { input: rest, pos, reference: self.reference, depth: self.depth, budget: self.budget.clone(), stalled: self.stalled, track: self.track.clone(), data: self.data.clone() }
+        )
+    }
+}
+
+impl<I, Data, T> Layout for State<I, Data, T> {
+    fn reference(&self) -> Pos {
+        self.reference
+    }
+
+    fn set_reference(&mut self, pos: Pos) {
+        self.reference = pos;
+    }
+}
+
+impl<I, Data, T> Memoize for State<I, Data, T>
+where
+    I: Input,
+    I::Token: AsChar,
+    Data: Clone,
+    T: Track
+{
+    fn memo_skip(&self, offset: usize, end: Pos) -> Self {
+        let mut state = self.clone();
+        state.input = self.input.split_at(offset).1;
+        state.pos = end;
+        state
+    }
+}
+
+impl<I, Data, T> Location for State<I, Data, T> {
+    type Location = Pos;
+
+    fn location(&self) -> Self::Location {
+        self.pos
+    }
+
+    fn progress(&self, other: &Self) -> Option<Ordering> {
+        (self.pos.source == other.pos.source).then(|| self.pos.offset.cmp(&other.pos.offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::char::{char, string};
+    use crate::combinator::{alt, take_while};
+    use crate::{Parser, ParseResult};
+
+    /// Text that fails the test if anything walks its tokens one by one.
+    #[derive(Debug, Clone)]
+    struct NoWalk<'a>(&'a str);
+
+    impl<'a> Input for NoWalk<'a> {
+        type Token = char;
+        type Tokens = std::str::Chars<'a>;
+
+        fn next(&mut self) -> Option<char> {
+            self.0.next()
+        }
+
+        fn peek(&mut self) -> Option<char> {
+            self.0.peek()
+        }
+
+        fn diff(&self, other: &Self) -> Self {
+            NoWalk(self.0.diff(&other.0))
+        }
+
+        fn tokens(&self) -> Self::Tokens {
+            panic!("walked the tokens of {:?}", self.0)
+        }
+
+        fn offset_to(&self, other: &Self) -> Option<usize> {
+            self.0.offset_to(&other.0)
+        }
+
+        fn starts_with(&self, literal: &str) -> Option<usize> {
+            Input::starts_with(&self.0, literal)
+        }
+
+        fn split_at(&self, offset: usize) -> (Self, Self) {
+            let (head, rest) = Input::split_at(&self.0, offset);
+            (NoWalk(head), NoWalk(rest))
+        }
+
+        fn split_at_position<P>(&self, pred: P) -> (Self, Self) where P: FnMut(&char) -> bool {
+            let (head, rest) = self.0.split_at_position(pred);
+            (NoWalk(head), NoWalk(rest))
+        }
+
+        fn len_utf8(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn offset_tracking_skips_bulk_tokens() {
+        type Text<'a> = State<NoWalk<'a>, (), Offset>;
+
+        let input = State::new(NoWalk("héllo wörld!")).tracking(Offset);
+        let result: ParseResult<Text, Text> = take_while(|ch: &char| ch.is_alphabetic()).parse(input);
+        let (word, rest) = result.unwrap();
+        assert_eq!((word.0, rest.pos().offset()), ("héllo", 6));
+
+        let result: ParseResult<Text, Text> = string(" wörld").parse(rest);
+        let (_, rest) = result.unwrap();
+        assert_eq!((rest.0, rest.pos().offset(), rest.pos().is_resolved()), ("!", 13, false));
+    }
+
+    #[test]
+    fn per_token_tracking_counts_columns_in_bulk() {
+        let result: ParseResult<State<&str>, State<&str>> = take_while(|ch: &char| *ch != '!').parse(State::new("hé\r\nwörld!"));
+        let (word, rest) = result.unwrap();
+        assert_eq!(*word, "hé\r\nwörld");
+        assert_eq!((rest.pos().line(), rest.pos().column(), rest.pos().offset()), (2, 6, 11));
+    }
+
+    #[test]
+    fn editor_tabs_after_offset_tracking() {
+        let result: ParseResult<char, State<&str, (), Editor>> = char('\t').parse(State::new("\tx").tracking(Offset).tracking(Editor::default()));
+        let rest = result.unwrap().1;
+        assert_eq!((rest.pos().column(), rest.pos().offset()), (4, 1));
+    }
+
+    #[test]
+    fn backtracking_shares_the_data() {
+        static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug)]
+        struct Counted;
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.fetch_add(1, Ordering::Relaxed);
+                Counted
+            }
+        }
+
+        let result: ParseResult<char, State<&str, Counted>> = alt((char('a').andr(char('b')), char('a').andr(char('c')))).parse(State::with_data(Counted, "ac"));
+        assert_eq!(result.unwrap().0, 'c');
+        assert_eq!(CLONES.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,352 @@
+//! Parsers for inputs of arbitrary tokens, such as the output of a separate lexer.
+//!
+//! Unlike the ones in [`char`](crate::char) these only need tokens to be comparable, and
+//! [`TokenStream`] carries each token's source span so errors point at the offending
+//! token rather than the rest of the input.
+//! # Example
+//! ```
+//! use rtor::{Parser, ParseResult, RichError};
+//! use rtor::token::{kind, token, Span, TokenKind, TokenStream};
+//! use rtor::combinator::sep_by1;
+//!
+//! #[derive(Debug, Clone, PartialEq)]
+//! enum Tok { Num(i64), Plus }
+//!
+//! impl TokenKind for Tok {
+//!     fn kind(&self) -> &'static str {
+//!         match self {
+//!             Tok::Num(_) => "Num",
+//!             Tok::Plus => "Plus",
+//!         }
+//!     }
+//! }
+//!
+//! type Input<'a> = TokenStream<'a, Tok>;
+//!
+//! fn sum(i: Input) -> ParseResult<i64, Input, RichError<Input>> {
+//!     sep_by1(kind(Tok::Num(0)), token(Tok::Plus))
+//!         .map(|nums| nums.iter().map(|n| match n { Tok::Num(n) => n, _ => &0 }).sum())
+//!         .parse(i)
+//! }
+//!
+//! // 1 + 22 +
+//! let tokens = [
+//!     (Tok::Num(1), Span::new(0, 1)),
+//!     (Tok::Plus, Span::new(2, 3)),
+//!     (Tok::Num(22), Span::new(4, 6)),
+//!     (Tok::Plus, Span::new(7, 8)),
+//! ];
+//!
+//! assert_eq!(sum(TokenStream::new(&tokens[..3])).unwrap().0, 23);
+//!
+//! let err = sum(TokenStream::new(&tokens[..2])).unwrap_err();
+//! assert_eq!(err.to_string(), "expected Num, found end of input at 3..3");
+//!
+//! let err = sum(TokenStream::new(&tokens[1..])).unwrap_err();
+//! assert_eq!(err.to_string(), "expected Num, found Plus at 2..3");
+//! ```
+
+use std::{
+    fmt,
+    iter::Map,
+    ops::Range,
+    slice::Iter,
+    cmp::Ordering,
+};
+
+use crate::{
+    Input,
+    Location,
+    ParseResult,
+    ParseError,
+    Expected,
+    Needed,
+    FindToken,
+    SourceSpan,
+    SourceId,
+};
+
+/// A byte range in the source a token was lexed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub source: SourceId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { source: SourceId::default(), start, end }
+    }
+
+    /// This span in the source `source`, see [`Sources`](crate::Sources).
+    pub fn in_source(self, source: SourceId) -> Self {
+        Self { source, ..self }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+impl SourceSpan for Span {
+    fn source_span(&self, _source: &str) -> Range<usize> {
+        self.start..self.end
+    }
+
+    fn source(&self) -> SourceId {
+        self.source
+    }
+}
+
+/// Tokens that fall into kinds, like the variants of an enum whatever their fields
+/// hold, for [`kind`] to match.
+pub trait TokenKind {
+    /// The name of this token's kind, which tokens of the same kind share and errors
+    /// expect.
+    fn kind(&self) -> &'static str;
+}
+
+/// A slice of tokens paired with their spans. Its location is the span of the next
+/// token, or an empty span just past the last one at the end.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TokenStream<'a, T> {
+    tokens: &'a [(T, Span)],
+    end: Span,
+}
+
+impl<'a, T> TokenStream<'a, T> {
+    pub fn new(tokens: &'a [(T, Span)]) -> Self {
+        Self {
+            tokens,
+            end: end_of(tokens, Span::default()),
+        }
+    }
+
+    pub fn as_slice(&self) -> &'a [(T, Span)] {
+        self.tokens
+    }
+}
+
+/// An empty span just past the last of `tokens`, or `otherwise` if there are none.
+fn end_of<T>(tokens: &[(T, Span)], otherwise: Span) -> Span {
+    tokens.last().map_or(otherwise, |(_, span)| Span { start: span.end, ..*span })
+}
+
+impl<T> Clone for TokenStream<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TokenStream<'_, T> {}
+
+impl<'a, T: Clone> Input for TokenStream<'a, T> {
+    type Token = T;
+    type Tokens = Map<Iter<'a, (T, Span)>, fn(&(T, Span)) -> T>;
+
+    fn next(&mut self) -> Option<Self::Token> {
+        let ((t, _), rest) = self.tokens.split_first()?;
+        self.tokens = rest;
+        Some(t.clone())
+    }
+
+    fn peek(&mut self) -> Option<Self::Token> {
+        self.tokens.first().map(|(t, _)| t.clone())
+    }
+
+    fn diff(&self, other: &Self) -> Self {
+        let tokens = self.tokens.diff(&other.tokens);
+        let start = self.location();
+        TokenStream { tokens, end: end_of(tokens, Span { end: start.start, ..start }) }
+    }
+
+    fn tokens(&self) -> Self::Tokens {
+        fn token<T: Clone>((t, _): &(T, Span)) -> T {
+            t.clone()
+        }
+        self.tokens.iter().map(token)
+    }
+
+    fn offset_to(&self, other: &Self) -> Option<usize> {
+        self.tokens.offset_to(&other.tokens)
+    }
+
+    fn split_at(&self, offset: usize) -> (Self, Self) {
+        let (head, rest) = self.tokens.split_at(offset.min(self.tokens.len()));
+        let start = self.location();
+        let end = end_of(head, Span { end: start.start, ..start });
+        (TokenStream { tokens: head, end }, TokenStream { tokens: rest, end: self.end })
+    }
+
+    fn split_at_position<P>(&self, mut pred: P) -> (Self, Self) where P: FnMut(&Self::Token) -> bool {
+        let offset = self.tokens.iter().position(|(t, _)| pred(t)).unwrap_or(self.tokens.len());
+        self.split_at(offset)
+    }
+}
+
+impl<T> Location for TokenStream<'_, T> {
+    type Location = Span;
+
+    fn location(&self) -> Self::Location {
+        self.tokens.first().map_or(self.end, |(_, span)| *span)
+    }
+
+    fn progress(&self, other: &Self) -> Option<Ordering> {
+        let (this, other) = (self.location(), other.location());
+        (this.source == other.source).then(|| this.start.cmp(&other.start))
+    }
+}
+
+/// Parse a token that satisfies `pred`.
+pub fn satisfy<F, I, E>(mut pred: F) -> impl FnMut(I) -> ParseResult<I::Token, I, E>
+where
+    I: Input,
+    F: FnMut(&I::Token) -> bool,
+    E: ParseError<I>
+{
+    move |mut input: I| {
+        match input.peek() {
+            Some(t) if pred(&t) => { input.next(); Ok((t, input)) },
+            None if input.is_partial() => Err(ParseError::incomplete(Needed::Size(1), input)),
+            _ => Err(ParseError::unexpect(input)),
+        }
+    }
+}
+
+/// Parse a token equal to `expected`.
+/// # Example
+/// ```
+/// use rtor::ParseResult;
+/// use rtor::token::token;
+///
+/// fn parser(i: &[u32]) -> ParseResult<u32, &[u32]> {
+///     token(7)(i)
+/// }
+///
+/// assert_eq!(parser(&[7, 8]), Ok((7, &[8][..])));
+/// assert!(parser(&[8]).is_err());
+/// ```
+pub fn token<I, E>(expected: I::Token) -> impl FnMut(I) -> ParseResult<I::Token, I, E>
+where
+    I: Input,
+    I::Token: PartialEq + fmt::Debug,
+    E: ParseError<I>
+{
+    move |mut input: I| {
+        match input.peek() {
+            Some(t) if t == expected => { input.next(); Ok((t, input)) },
+            None if input.is_partial() => Err(ParseError::incomplete(Needed::Size(1), input)),
+            _ => Err(ParseError::expected(Expected::Label(format!("{:?}", expected)), input)),
+        }
+    }
+}
+
+/// Parse a token of the same [kind](TokenKind) as `example`, see the
+/// [module](crate::token) example.
+pub fn kind<I, E>(example: I::Token) -> impl FnMut(I) -> ParseResult<I::Token, I, E>
+where
+    I: Input,
+    I::Token: TokenKind,
+    E: ParseError<I>
+{
+    let kind = example.kind();
+    move |mut input: I| {
+        match input.peek() {
+            Some(t) if t.kind() == kind => { input.next(); Ok((t, input)) },
+            None if input.is_partial() => Err(ParseError::incomplete(Needed::Size(1), input)),
+            _ => Err(ParseError::expected(Expected::Label(kind.to_owned()), input)),
+        }
+    }
+}
+
+/// Parse any token.
+pub fn any<I, E>(input: I) -> ParseResult<I::Token, I, E>
+where
+    I: Input,
+    E: ParseError<I>
+{
+    satisfy(|_| true)(input)
+}
+
+/// Parse a token that is in `tokens`.
+pub fn one_of<I, E, F>(tokens: F) -> impl FnMut(I) -> ParseResult<I::Token, I, E>
+where
+    I: Input,
+    F: FindToken<I::Token>,
+    E: ParseError<I>
+{
+    satisfy(move |t: &I::Token| tokens.find_token(t))
+}
+
+/// Parse a token that is not in `tokens`.
+pub fn none_of<I, E, F>(tokens: F) -> impl FnMut(I) -> ParseResult<I::Token, I, E>
+where
+    I: Input,
+    F: FindToken<I::Token>,
+    E: ParseError<I>
+{
+    satisfy(move |t: &I::Token| !tokens.find_token(t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, RichError};
+    use crate::combinator::many;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Tok { Num(i64), Name(&'static str) }
+
+    impl TokenKind for Tok {
+        fn kind(&self) -> &'static str {
+            match self {
+                Tok::Num(_) => "number",
+                Tok::Name(_) => "name",
+            }
+        }
+    }
+
+    // 1  23   x
+    fn tokens() -> [(Tok, Span); 3] {
+        [(Tok::Num(1), Span::new(0, 1)), (Tok::Num(23), Span::new(3, 5)), (Tok::Name("x"), Span::new(8, 9))]
+    }
+
+    #[test]
+    fn kinds_are_named_by_the_token() {
+        let tokens = tokens();
+        let input = TokenStream::new(&tokens);
+        let (nums, rest) = many(kind::<_, RichError<_>>(Tok::Num(0))).parse(input).unwrap();
+        assert_eq!(nums, [Tok::Num(1), Tok::Num(23)]);
+        let err = kind::<_, RichError<_>>(Tok::Num(0)).parse(rest).unwrap_err();
+        assert_eq!(err.to_string(), "expected number, found Name(\"x\") at 8..9");
+    }
+
+    #[test]
+    fn consumed_tokens_end_at_the_last_one() {
+        let tokens = tokens();
+        let input = TokenStream::new(&tokens);
+        let (_, rest) = many(kind::<_, RichError<_>>(Tok::Num(0))).parse(input).unwrap();
+        let consumed = input.diff(&rest);
+        assert_eq!(consumed.as_slice(), &tokens[..2]);
+        let (_, end) = consumed.split_at(2);
+        assert_eq!(end.location(), Span::new(5, 5));
+        assert_eq!(input.split_at(2).0.split_at(2).1.location(), Span::new(5, 5));
+    }
+
+    #[test]
+    fn nothing_consumed_is_empty_at_the_start() {
+        let tokens = tokens();
+        let input = TokenStream::new(&tokens[1..]);
+        assert_eq!(input.diff(&input).location(), Span::new(3, 3));
+        assert_eq!(input.split_at(0).0.location(), Span::new(3, 3));
+    }
+}